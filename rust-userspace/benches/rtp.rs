@@ -24,13 +24,16 @@ fn bench_rtp_send_receive(c: &mut Criterion) {
     let mut group = c.benchmark_group("rtp");
 
     group.bench_function("send_receive", |b| {
+        const BENCH_SSRC: u32 = 0x424e4348; // "BNCH"
+        const BENCH_PAYLOAD_TYPE: u8 = 98;
+
         let (sender_socket, receiver_socket) = setup_sockets();
-        let mut sender = RtpSizedPayloadSender::<TestPayload>::new(sender_socket);
-        let receiver = RtpSizedPayloadReceiver::<TestPayload, 32>::new(receiver_socket);
+        let mut sender = RtpSizedPayloadSender::<TestPayload>::new(sender_socket, BENCH_SSRC, BENCH_PAYLOAD_TYPE);
+        let receiver = RtpSizedPayloadReceiver::<TestPayload, 32>::new(receiver_socket, BENCH_SSRC);
 
         b.iter(|| {
             // Send a packet
-            sender.send(|payload: &mut TestPayload| {
+            sender.send(0, false, |payload: &mut TestPayload| {
                 payload.data = black_box([42u8; 64]);
             });
 
@@ -0,0 +1,77 @@
+//! ChaCha20-Poly1305 AEAD framing (RFC 8439 section 2.8) over [`crate::chacha20`]'s keystream and
+//! [`crate::poly1305`]'s one-time authenticator — what [`crate::transport::AuthenticatedEncryptedTransport`]
+//! uses to wrap a packet so a tampered or forged datagram is rejected outright instead of being
+//! decrypted into garbage macroblocks. Plain [`crate::transport::EncryptedTransport`] only keeps
+//! traffic off the wire from being read; this adds the integrity half lonelyradio-style framing
+//! is missing without a MAC.
+
+use crate::{chacha20, poly1305};
+
+pub const KEY_BYTES: usize = chacha20::KEY_BYTES;
+pub const NONCE_BYTES: usize = chacha20::NONCE_BYTES;
+pub const TAG_BYTES: usize = 16;
+
+/// Builds the 96-bit nonce this module's `seal`/`open` expect from a per-stream random salt and a
+/// monotonically increasing packet counter: the low 4 bytes carry the counter, the high 8 the
+/// salt, so as long as `salt` is unique per session the (salt, counter) pair this produces never
+/// repeats for the life of that session — reusing a ChaCha20 nonce leaks the XOR of the two
+/// plaintexts, so that uniqueness is load-bearing.
+pub fn nonce_from_salt_and_counter(salt: [u8; NONCE_BYTES - 4], counter: u32) -> [u8; NONCE_BYTES] {
+    let mut nonce = [0u8; NONCE_BYTES];
+    nonce[..4].copy_from_slice(&counter.to_le_bytes());
+    nonce[4..].copy_from_slice(&salt);
+    nonce
+}
+
+/// The one-time Poly1305 key RFC 8439 derives per-message: the first 32 bytes of the ChaCha20
+/// keystream at block counter 0, the one block [`seal`]/[`open`] never spend on the actual
+/// ciphertext (they start at counter 1).
+fn derive_poly1305_key(key: &[u8; KEY_BYTES], nonce: &[u8; NONCE_BYTES]) -> [u8; 32] {
+    let mut block = [0u8; 32];
+    chacha20::apply_keystream(key, nonce, 0, &mut block);
+    block
+}
+
+/// Encrypts `plaintext` under `key`/`nonce` and appends a 16-byte Poly1305 tag computed over the
+/// ciphertext, so [`open`] can reject a tampered packet before ever running it back through the
+/// keystream.
+pub fn seal(key: &[u8; KEY_BYTES], nonce: &[u8; NONCE_BYTES], plaintext: &[u8]) -> Vec<u8> {
+    let mut sealed = plaintext.to_vec();
+    chacha20::apply_keystream(key, nonce, 1, &mut sealed);
+    let tag = poly1305::compute_tag(&derive_poly1305_key(key, nonce), &sealed);
+    sealed.extend_from_slice(&tag);
+    sealed
+}
+
+/// Inverse of [`seal`]: verifies `sealed`'s trailing tag in constant time before decrypting
+/// anything, returning `None` if it doesn't match. Callers should treat `None` exactly like a
+/// lost packet (see [`crate::transport::AuthenticatedEncryptedTransport`]) rather than a decoding
+/// error — there's no way to tell a forged packet from simple corruption, and the codec already
+/// tolerates the latter.
+pub fn open(key: &[u8; KEY_BYTES], nonce: &[u8; NONCE_BYTES], sealed: &[u8]) -> Option<Vec<u8>> {
+    if sealed.len() < TAG_BYTES {
+        return None;
+    }
+    let (ciphertext, received_tag) = sealed.split_at(sealed.len() - TAG_BYTES);
+    let expected_tag = poly1305::compute_tag(&derive_poly1305_key(key, nonce), ciphertext);
+    if !tags_match(received_tag.try_into().unwrap(), &expected_tag) {
+        return None;
+    }
+
+    let mut plaintext = ciphertext.to_vec();
+    chacha20::apply_keystream(key, nonce, 1, &mut plaintext);
+    Some(plaintext)
+}
+
+/// Constant-time tag comparison: folds every byte difference into a single accumulator with
+/// `|=` rather than comparing byte-by-byte with an early-returning `==`, then turns the
+/// accumulator into an equal/not-equal flag via two's-complement negation (`wrapping_neg` then an
+/// arithmetic shift) instead of a final `== 0` branch — so how many leading bytes of the tag
+/// matched before the first difference can't leak through how long verification takes.
+fn tags_match(received: &[u8; TAG_BYTES], expected: &[u8; TAG_BYTES]) -> bool {
+    let mut diff: u8 = 0;
+    for i in 0..TAG_BYTES {
+        diff |= received[i] ^ expected[i];
+    }
+    (diff as i64).wrapping_neg() >> 63 == 0
+}
@@ -1,15 +1,98 @@
 use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
-use std::time::Duration;
+use std::collections::VecDeque;
 use std::net::Ipv4Addr;
-use crate::{rtp, udp_connect_retry, RECV_AUDIO_PORT, RECV_IP, SEND_IP, SEND_AUDIO_PORT};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use crate::{audio_codec, rtp, rtp_epoch, rtp_rfc3550, udp_connect_retry, AUDIO_PAYLOAD_TYPE, AUDIO_SSRC, RECV_AUDIO_PORT, RECV_IP, SEND_IP, SEND_AUDIO_PORT};
+use std::net::UdpSocket;
 
 pub const AUDIO_SAMPLE_COUNT: usize = 1024;
 pub const AUDIO_FREQUENCY: i32 = 44100;
 pub const AUDIO_BUFFER_LENGTH: usize = 1024;
 
+/// Nominal spacing between audio packets, in milliseconds. Feeds
+/// [`rtp::RtpReceiver::target_buffer_span`] so the jitter buffer's depth target is expressed in
+/// packets regardless of the stream's sample rate.
+const AUDIO_PACKET_INTERVAL_MILLIS: f64 = (1000 * AUDIO_SAMPLE_COUNT as u64) as f64 / AUDIO_FREQUENCY as f64;
+
+/// Size, in bytes, of the presentation-timestamp header each audio RTP payload is prefixed with.
+const AUDIO_FRAME_HEADER_BYTES: usize = size_of::<u64>();
+
+/// Maximum size of one audio RTP payload: the pts header plus the worst case a compressed frame
+/// can encode to. Frames are variable-length (see [`audio_codec`]), so this is a ceiling, not the
+/// typical size.
+const MAX_AUDIO_PACKET_BYTES: usize = AUDIO_FRAME_HEADER_BYTES + audio_codec::MAX_ENCODED_FRAME_BYTES;
+
+type RtpAudioSender = rtp::RtpSlicePayloadSender<u8, MAX_AUDIO_PACKET_BYTES>;
+type RtpAudioReceiver = rtp::RtpSlicePayloadReceiver<u8, MAX_AUDIO_PACKET_BYTES, AUDIO_BUFFER_LENGTH>;
+
+/// The stream's presentation clock. Defaults to tracking the audio path, since audio playback is
+/// the one side that can't be sped up or slowed down without becoming audibly wrong; the video
+/// loop reads [`MasterClock::now_millis`] to decide whether to present, delay, or drop a frame.
+///
+/// Cloning shares the same underlying counter, so the audio callback (which owns the only
+/// writer) and the video loop (a reader) can each hold their own handle.
+#[derive(Clone)]
+pub struct MasterClock(Arc<AtomicU64>);
+
+impl MasterClock {
+    fn new() -> Self {
+        MasterClock(Arc::new(AtomicU64::new(0)))
+    }
+
+    /// Current position of the master clock, in the same `rtp_epoch`-relative milliseconds as
+    /// presentation timestamps.
+    pub fn now_millis(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Jumps the clock to the pts of audio that's about to play, e.g. on receiving a fresh packet.
+    fn set_millis(&self, pts_millis: u64) {
+        self.0.store(pts_millis, Ordering::Relaxed);
+    }
+
+    /// Advances the clock by the duration of samples actually played, e.g. after repeating the
+    /// last packet because none had arrived.
+    fn advance_millis(&self, millis: u64) {
+        self.0.fetch_add(millis, Ordering::Relaxed);
+    }
+
+    /// ffplay-style A/V sync policy: compares a video frame's presentation timestamp against this
+    /// clock and decides whether it should be shown now, held back, or skipped outright.
+    pub fn sync_video_frame(&self, video_pts_millis: u64) -> SyncDecision {
+        let diff = video_pts_millis as i64 - self.now_millis() as i64;
+
+        if diff > AV_SYNC_THRESHOLD_MILLIS as i64 {
+            SyncDecision::Delay((diff - AV_SYNC_THRESHOLD_MILLIS as i64) as u64)
+        } else if diff < -(AV_SYNC_THRESHOLD_MILLIS as i64) {
+            SyncDecision::Drop
+        } else {
+            SyncDecision::Present
+        }
+    }
+}
+
+/// Window around the master clock within which a video frame is considered "in sync" and
+/// presented immediately, rather than delayed or dropped. ffplay uses the same default.
+pub const AV_SYNC_THRESHOLD_MILLIS: u64 = 40;
+
+/// What [`MasterClock::sync_video_frame`] recommends doing with a decoded video frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SyncDecision {
+    /// The frame is running ahead of the master clock; sleep this many milliseconds first.
+    Delay(u64),
+    /// The frame has fallen far enough behind the master clock that presenting it would just
+    /// show stale video; skip it and move on to the next frame.
+    Drop,
+    /// The frame is within [`AV_SYNC_THRESHOLD_MILLIS`] of the master clock; show it now.
+    Present,
+}
+
 pub struct AudioCallbackData {
     last: [f32; AUDIO_SAMPLE_COUNT],
-    recv: rtp::RtpSizedPayloadReceiver<[f32; AUDIO_SAMPLE_COUNT], AUDIO_BUFFER_LENGTH>,
+    recv: RtpAudioReceiver,
+    clock: MasterClock,
 }
 
 impl AudioCallback for AudioCallbackData {
@@ -20,7 +103,7 @@ impl AudioCallback for AudioCallbackData {
 
         // If the circular buffer hasn't seen enough future packets, wait for more to arrive
         // Handles the case: sender is falling behind in sending packets.
-        while locked_receiver.early_latest_span() < 5 {
+        while locked_receiver.early_latest_span() < locked_receiver.target_buffer_span(AUDIO_PACKET_INTERVAL_MILLIS) {
             log::debug!("Sleeping and waiting for more packets to arrive. Early-latest span {}", locked_receiver.early_latest_span());
             drop(locked_receiver);
             std::thread::sleep(Duration::from_millis(
@@ -29,28 +112,48 @@ impl AudioCallback for AudioCallbackData {
             locked_receiver = self.recv.lock_receiver();
         }
 
+        // `consume_earliest_packet` itself now gates on the adaptive playout delay (and on
+        // whether the slot is even filled), so `None` covers both "not ready yet" and "gap".
         let received_packet = locked_receiver.consume_earliest_packet();
 
-        if let Some(packet) = received_packet.get_data() {
+        if let Some(packet) = received_packet.as_ref().and_then(|p| p.get_data()) {
             log::info!("Playing packet with seq: {:?}", packet.header);
 
-            out.copy_from_slice(&packet.data);
+            let pts_millis = u64::from_le_bytes(
+                packet.data[..AUDIO_FRAME_HEADER_BYTES].try_into().unwrap(),
+            );
+            audio_codec::decode(&packet.data[AUDIO_FRAME_HEADER_BYTES..], &mut self.last);
 
-            self.last = packet.data;
+            out.copy_from_slice(&self.last);
+            self.clock.set_millis(pts_millis);
         } else {
-            log::info!("No packet to play. Playing last received packet again.");
+            // Conceal the gap by repeating the last decoded block rather than going silent.
+            log::info!("No packet to play. Playing last decoded block again.");
+            out.copy_from_slice(&self.last);
         }
+
+        self.clock.advance_millis((out.len() as u64 * 1000) / AUDIO_FREQUENCY as u64);
     }
 }
 
 /// Start playing audio from a UDP stream. Audio will play until returned device is dropped.
 /// Ensure that the frequency, sample count and bit depth of the sender and receiver match.
-
-pub fn play_audio(audio_subsystem: &sdl2::AudioSubsystem) -> AudioDevice<AudioCallbackData> {
+///
+/// This already drains [`RtpAudioReceiver`] into real output on sdl2's own pull-based audio
+/// callback (see [`AudioCallbackData::callback`]) rather than a logging stub: underrun repeats
+/// the last decoded block (see [`AudioCallbackData::last`]) instead of going silent, and sdl2
+/// itself applies backpressure on the buffer sdl2 owns internally, so there's no separate
+/// overrun case to handle on this end. There's no cpal integration or `err_fn` callback in this
+/// crate to hang a cpal-flavored output stream off of — sdl2's `AudioCallback`/`AudioDevice` is
+/// the one playback/capture abstraction this crate uses, on both the send and receive sides.
+pub fn play_audio(audio_subsystem: &sdl2::AudioSubsystem) -> (AudioDevice<AudioCallbackData>, MasterClock) {
     let sock = udp_connect_retry((Ipv4Addr::UNSPECIFIED, RECV_AUDIO_PORT));
     sock.connect((SEND_IP, SEND_AUDIO_PORT)).unwrap();
 
-    let recv: rtp::RtpSizedPayloadReceiver<[f32; AUDIO_SAMPLE_COUNT], AUDIO_BUFFER_LENGTH> = rtp::RtpReceiver::new(sock);
+    let recv: RtpAudioReceiver = rtp::RtpReceiver::new(sock, AUDIO_SSRC);
+    recv.lock_receiver().set_packet_interval_millis(AUDIO_PACKET_INTERVAL_MILLIS);
+    let clock = MasterClock::new();
+    let callback_clock = clock.clone();
 
     let desired_spec = AudioSpecDesired {
         freq: Some(AUDIO_FREQUENCY),
@@ -67,6 +170,7 @@ pub fn play_audio(audio_subsystem: &sdl2::AudioSubsystem) -> AudioDevice<AudioCa
             AudioCallbackData {
                 last: [0.0; AUDIO_SAMPLE_COUNT],
                 recv,
+                clock: callback_clock,
             }
         })
         .unwrap();
@@ -76,32 +180,170 @@ pub fn play_audio(audio_subsystem: &sdl2::AudioSubsystem) -> AudioDevice<AudioCa
     std::thread::sleep(Duration::from_secs(1));
 
     device.resume();
-    device
+    (device, clock)
+}
+
+/// Accumulates captured audio callback chunks, which SDL2 may hand over in whatever size the
+/// capture device feels like, and lets a consumer pull out exactly [`AUDIO_SAMPLE_COUNT`] samples
+/// at a time, so outgoing packet sizes stay aligned with what [`AudioCallbackData`] expects.
+struct CaptureRingBuffer {
+    samples: VecDeque<f32>,
+}
+
+impl CaptureRingBuffer {
+    fn new() -> Self {
+        CaptureRingBuffer { samples: VecDeque::with_capacity(2 * AUDIO_SAMPLE_COUNT) }
+    }
+
+    fn push(&mut self, chunk: &[f32]) {
+        self.samples.extend(chunk.iter().copied());
+    }
+
+    /// Removes and returns exactly [`AUDIO_SAMPLE_COUNT`] samples, if that many are buffered yet.
+    fn take_exact(&mut self) -> Option<[f32; AUDIO_SAMPLE_COUNT]> {
+        if self.samples.len() < AUDIO_SAMPLE_COUNT {
+            return None;
+        }
+        let mut block = [0.0; AUDIO_SAMPLE_COUNT];
+        for slot in block.iter_mut() {
+            *slot = self.samples.pop_front().unwrap();
+        }
+        Some(block)
+    }
+}
+
+struct CaptureCallback {
+    buffer: Arc<Mutex<CaptureRingBuffer>>,
+}
+
+impl AudioCallback for CaptureCallback {
+    type Channel = f32;
+
+    fn callback(&mut self, input: &mut [f32]) {
+        self.buffer.lock().unwrap().push(input);
+    }
+}
+
+/// A live microphone capture stream, opened against the default input device. Keep this alive
+/// for as long as capture should continue; dropping it stops the device.
+pub struct AudioInput {
+    _device: AudioDevice<CaptureCallback>,
+    buffer: Arc<Mutex<CaptureRingBuffer>>,
+}
+
+impl AudioInput {
+    /// Opens the default capture device at [`AUDIO_FREQUENCY`]/mono/[`AUDIO_SAMPLE_COUNT`],
+    /// mirroring how [`play_audio`] opens its playback device.
+    pub fn new(audio_subsystem: &sdl2::AudioSubsystem) -> Self {
+        let buffer = Arc::new(Mutex::new(CaptureRingBuffer::new()));
+        let callback_buffer = buffer.clone();
+
+        let desired_spec = AudioSpecDesired {
+            freq: Some(AUDIO_FREQUENCY),
+            // mono
+            channels: Some(1),
+            samples: Some(AUDIO_SAMPLE_COUNT as u16),
+        };
+
+        let device = audio_subsystem
+            .open_capture(None, &desired_spec, |_spec| CaptureCallback { buffer: callback_buffer })
+            .unwrap();
+
+        device.resume();
+
+        AudioInput { _device: device, buffer }
+    }
+
+    /// Blocks until at least [`AUDIO_SAMPLE_COUNT`] samples have been captured, then consumes and
+    /// returns exactly that many.
+    pub fn recv_block(&self) -> [f32; AUDIO_SAMPLE_COUNT] {
+        loop {
+            if let Some(block) = self.buffer.lock().unwrap().take_exact() {
+                return block;
+            }
+            std::thread::sleep(Duration::from_millis(
+                (1000 * AUDIO_SAMPLE_COUNT as u64) / (AUDIO_FREQUENCY as u64) / 4,
+            ));
+        }
+    }
+}
+
+/// Which RTP framing [`send_audio`] emits, selectable per stream: [`Self::Compact`] is this
+/// crate's existing bespoke [`rtp::RtpSender`] format; [`Self::Rfc3550Aac`] emits real RFC-3550
+/// headers via [`rtp_rfc3550`], packetized the way RFC 3016's MP4A-LATM payloader does, so the
+/// stream is readable by ordinary RTP tooling instead of only another copy of this crate. See
+/// [`rtp_rfc3550`]'s module doc for why the two don't share wire-format plumbing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioHeaderFormat {
+    Compact,
+    Rfc3550Aac,
+}
+
+/// Per-format state [`send_audio`] threads through its loop — the [`AudioHeaderFormat`] choice
+/// made concrete once at startup instead of re-matched on every frame.
+enum AudioSender {
+    Compact(RtpAudioSender),
+    Rfc3550Aac { sock: UdpSocket, sequence_number: u16 },
 }
 
 /// Start sending audio over a UDP stream. Audio will be sent indefinitely.
-pub fn send_audio() -> ! {
+///
+/// `quantization_shift` is read fresh for every frame, so a caller relaying the receiver's
+/// WPM-derived control feedback (mirroring `send.rs`'s `process_block` `quality` parameter) can
+/// degrade the audio bitrate in step with the video quality, without restarting the stream.
+pub fn send_audio(
+    audio_subsystem: &sdl2::AudioSubsystem,
+    quantization_shift: Arc<RwLock<u32>>,
+    format: AudioHeaderFormat,
+) -> ! {
     let sock = udp_connect_retry((Ipv4Addr::UNSPECIFIED, SEND_AUDIO_PORT));
     sock.connect((RECV_IP, RECV_AUDIO_PORT)).unwrap();
-    let mut sender: rtp::RtpSizedPayloadSender<[f32; AUDIO_SAMPLE_COUNT]> = rtp::RtpSizedPayloadSender::new(sock);
-    
-    let mut time = 0.0;
-    let mut audio_wav_reader = std::iter::from_fn(move || {
-        time += 1.0 / AUDIO_FREQUENCY as f32;
-        Some(0.5 * (2.0 * std::f32::consts::PI * 440.0 * time).sin())
-    });
+    let mut sender = match format {
+        AudioHeaderFormat::Compact => {
+            AudioSender::Compact(rtp::RtpSender::new(sock, AUDIO_SSRC, AUDIO_PAYLOAD_TYPE))
+        }
+        AudioHeaderFormat::Rfc3550Aac => AudioSender::Rfc3550Aac { sock, sequence_number: 0 },
+    };
+
+    let mic = AudioInput::new(audio_subsystem);
+    let mut encoded = Vec::with_capacity(audio_codec::MAX_ENCODED_FRAME_BYTES);
 
     log::info!("Starting to send audio!");
 
     loop {
-        sender.send(|bytes: &mut [f32; AUDIO_SAMPLE_COUNT]| {
-            for idx in 0..AUDIO_SAMPLE_COUNT {
-                bytes[idx] = audio_wav_reader.next().unwrap();
+        let samples = mic.recv_block();
+        let pts_millis = rtp_epoch().elapsed().as_millis() as u64;
+
+        encoded.clear();
+        let shift = *quantization_shift.read().unwrap();
+        audio_codec::encode(&samples, shift, &mut encoded);
+
+        match &mut sender {
+            AudioSender::Compact(sender) => {
+                sender.send_bytes(pts_millis as u32, true, |mem| {
+                    mem[..AUDIO_FRAME_HEADER_BYTES].copy_from_slice(&pts_millis.to_le_bytes());
+                    mem[AUDIO_FRAME_HEADER_BYTES..AUDIO_FRAME_HEADER_BYTES + encoded.len()]
+                        .copy_from_slice(&encoded);
+                    AUDIO_FRAME_HEADER_BYTES + encoded.len()
+                });
             }
-        });
-        std::thread::sleep(Duration::from_millis(
-            (1000 * AUDIO_SAMPLE_COUNT as u64) / (AUDIO_FREQUENCY as u64),
-        ));
-        log::trace!("Sent audio packet.");
+            AudioSender::Rfc3550Aac { sock, sequence_number } => {
+                // RFC 3016's "sampling instant" timestamp, derived the same way the compact
+                // format's pts is (from `rtp_epoch`), just in samples instead of milliseconds.
+                let timestamp = (pts_millis * AUDIO_FREQUENCY as u64 / 1000) as u32;
+                if let Err(e) = rtp_rfc3550::send_aac_access_unit(
+                    sock,
+                    AUDIO_SSRC,
+                    AUDIO_PAYLOAD_TYPE,
+                    sequence_number,
+                    timestamp,
+                    audio_codec::MAX_ENCODED_FRAME_BYTES,
+                    &encoded,
+                ) {
+                    log::error!("failed to send AAC access unit: {e}");
+                }
+            }
+        }
+        log::trace!("Sent audio packet ({} bytes compressed).", encoded.len());
     }
 }
\ No newline at end of file
@@ -0,0 +1,87 @@
+//! A simple lossy codec for audio frames, standing in for a perceptual codec like Opus.
+//!
+//! Each `f32` sample is quantized to 16-bit PCM, delta-coded against the previous sample, and
+//! the deltas are zigzag + LEB128 varint encoded, so quiet or slowly-changing audio (the common
+//! case) collapses to a fraction of its raw size while silence-to-loud transients still round-trip
+//! losslessly modulo quantization. What matters to the RTP path ([`crate::audio`]) is that encoded
+//! frames are variable-length, unlike the raw `[f32; AUDIO_SAMPLE_COUNT]` they replace.
+//!
+//! [`encode`]'s `quantization_shift` is this codec's target-bitrate knob (see
+//! [`crate::wpm::wpm_to_audio_quantization_shift`]): [`decode`] doesn't need to know it, since a
+//! coarser sample is just a normal sample with its low bits zeroed, not a different wire format.
+
+use crate::audio::AUDIO_SAMPLE_COUNT;
+
+/// Worst case encoded size of one frame: every sample's delta needs the full 3-byte varint.
+pub const MAX_ENCODED_FRAME_BYTES: usize = 3 * AUDIO_SAMPLE_COUNT;
+
+/// Highest `quantization_shift` [`encode`] accepts. Bounded well below the 16-bit sample width so
+/// the worst case still carries audible (if coarse) signal, rather than shifting a sample to zero.
+pub const MAX_QUANTIZATION_SHIFT: u32 = 8;
+
+/// Encodes one frame of samples, appending the variable-length result to `out`.
+///
+/// `quantization_shift` zeroes out that many low bits of each 16-bit quantized sample before
+/// delta-coding it, trading fidelity for rate the way a real Opus encoder's target-bitrate setting
+/// would: coarser samples repeat more often and collapse to smaller zigzag deltas, so the varint
+/// stream shrinks. `0` keeps full quality; see [`MAX_QUANTIZATION_SHIFT`] for the ceiling.
+pub fn encode(samples: &[f32; AUDIO_SAMPLE_COUNT], quantization_shift: u32, out: &mut Vec<u8>) {
+    let quantization_shift = quantization_shift.min(MAX_QUANTIZATION_SHIFT);
+    let mut prev: i16 = 0;
+    for &sample in samples {
+        let quantized = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        let quantized = (quantized >> quantization_shift) << quantization_shift;
+        let delta = quantized.wrapping_sub(prev);
+        prev = quantized;
+        write_varint(zigzag_encode(delta), out);
+    }
+}
+
+/// Decodes a frame previously written by [`encode`] back into `out`.
+pub fn decode(mut bytes: &[u8], out: &mut [f32; AUDIO_SAMPLE_COUNT]) {
+    let mut prev: i16 = 0;
+    for slot in out.iter_mut() {
+        let delta = zigzag_decode(read_varint(&mut bytes));
+        prev = prev.wrapping_add(delta);
+        *slot = prev as f32 / i16::MAX as f32;
+    }
+}
+
+/// Maps a signed delta to an unsigned value with small magnitudes (in either direction) mapping
+/// to small outputs, so [`write_varint`] can spend fewer bytes on the common case.
+fn zigzag_encode(v: i16) -> u32 {
+    (((v as i32) << 1) ^ ((v as i32) >> 15)) as u32
+}
+
+fn zigzag_decode(v: u32) -> i16 {
+    ((v >> 1) as i32 ^ -((v & 1) as i32)) as i16
+}
+
+/// Writes `v` as a little-endian base-128 varint: 7 value bits per byte, continuation in the
+/// high bit.
+fn write_varint(mut v: u32, out: &mut Vec<u8>) {
+    loop {
+        let byte = (v & 0x7f) as u8;
+        v >>= 7;
+        if v == 0 {
+            out.push(byte);
+            break;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn read_varint(bytes: &mut &[u8]) -> u32 {
+    let mut result = 0u32;
+    let mut shift = 0;
+    loop {
+        let byte = bytes[0];
+        *bytes = &bytes[1..];
+        result |= ((byte & 0x7f) as u32) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    result
+}
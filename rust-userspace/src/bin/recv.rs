@@ -4,9 +4,13 @@ use run_louder::*;
 
 use bytes::Buf;
 use sdl2::{self, pixels::{Color, PixelFormatEnum}, rect::Rect};
-use video::{decode_quantized_macroblock, dequantize_macroblock, MutableYUVFrame};
+use video::{
+    build_codebook, decode_block, decode_inter_macroblock, deblock_frame, encode_block,
+    macroblock_from_solid_color, Macroblock, MacroblockWithPosition, MotionVector,
+    MutableYUVFrame, YUVFrame, YUVFrameMacroblockIterator,
+};
 use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
-use std::{io::Write, net::{Ipv4Addr, UdpSocket}, thread::sleep, time::Duration};
+use std::{io::Write, net::{Ipv4Addr, UdpSocket}, thread::sleep, time::{Duration, Instant}};
 
 use simplelog::WriteLogger;
 
@@ -16,8 +20,154 @@ struct VideoPacket {
     data: [u8; 1504]
 }
 
+const BLOCK_WRITTEN_WIDTH: usize = (VIDEO_WIDTH as usize) / MACROBLOCK_X_DIM;
+const BLOCK_WRITTEN_HEIGHT: usize = (VIDEO_HEIGHT as usize) / MACROBLOCK_Y_DIM;
+
+/// Wire-format `mode` byte values written by `send.rs`'s `PacketFraming` (see
+/// `video::MacroblockDecision`).
+const MODE_CODED: u8 = 0;
+const MODE_FILL: u8 = 1;
+const MODE_SKIP: u8 = 2;
+
+/// Maps a `decision_grid` byte to the RGB color the debug overlay paints that macroblock:
+/// green = skipped, yellow = filled, red = fully coded, black = never written this frame (lost).
+fn decision_mode_color(mode: u8) -> (u8, u8, u8) {
+    match mode {
+        MODE_SKIP => (0, 200, 0),
+        MODE_FILL => (220, 200, 0),
+        MODE_CODED => (200, 0, 0),
+        _ => (0, 0, 0),
+    }
+}
+
+/// Decodes one consumed video RTP payload (a `frame_count: u32` prefix followed by repeated
+/// `(x, y, mode, [quality], mode-specific payload)` tuples, terminated by a `(u16::MAX, u16::MAX)`
+/// sentinel — see `send.rs`'s `PacketFraming::Legacy`) into `buffer`, a YUY2-format frame, marking
+/// off which macroblock slots it touched in `block_written` and recording each slot's coding mode
+/// in `decision_grid` (for the skip/fill/coded debug overlay). `previous_grid`/`previous_mv_grid`
+/// are the previous frame's reconstructed macroblocks/motion vectors (see
+/// `video::decode_inter_macroblock`) that a `MODE_CODED` macroblock's motion compensation is
+/// rebuilt against; `mv_grid` is updated in place with each decoded position's motion vector so
+/// the caller can feed it back in as next frame's `previous_mv_grid`. Shared by the live decode
+/// loop and [`capture::CaptureReader`] replay, so both draw identically from the same payload
+/// bytes. Returns how many macroblocks were decoded.
+fn decode_video_packet_into_frame(
+    mut cursor: &[u8],
+    buffer: &mut [u8],
+    block_written: &mut [[bool; BLOCK_WRITTEN_WIDTH]; BLOCK_WRITTEN_HEIGHT],
+    decision_grid: &mut [[u8; BLOCK_WRITTEN_WIDTH]; BLOCK_WRITTEN_HEIGHT],
+    previous_grid: &[Macroblock],
+    previous_mv_grid: &[MotionVector],
+    mv_grid: &mut [MotionVector],
+) -> usize {
+    let _packet_frame_count = cursor.get_u32();
+    let mut decoded = 0usize;
+    loop {
+        let x = cursor.get_u16() as usize;
+        let y = cursor.get_u16() as usize;
+
+        if (x == u16::MAX as usize) && (y == u16::MAX as usize) {
+            break;
+        }
+
+        let mode = cursor.get_u8();
+        let gx = x / MACROBLOCK_X_DIM;
+        let gy = y / MACROBLOCK_Y_DIM;
+        block_written[gy][gx] = true;
+        decision_grid[gy][gx] = mode;
+
+        match mode {
+            MODE_SKIP => {
+                // No payload: whatever `buffer` already holds at this macroblock from the
+                // previous frame (`with_lock` never clears it) is exactly what the sender decided
+                // to keep, so there's nothing to copy in.
+            }
+            MODE_FILL => {
+                let luma = cursor.get_u8();
+                let u = cursor.get_u8();
+                let v = cursor.get_u8();
+                let macroblock = macroblock_from_solid_color(luma, u, v);
+                macroblock.copy_to_yuv422_frame(MutableYUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, buffer), x, y);
+            }
+            MODE_CODED => {
+                let quality = cursor.get_f64();
+                let (macroblock, mv, rest) = decode_inter_macroblock(
+                    cursor,
+                    previous_grid,
+                    previous_mv_grid,
+                    BLOCK_WRITTEN_WIDTH,
+                    BLOCK_WRITTEN_HEIGHT,
+                    gx,
+                    gy,
+                    quality,
+                );
+                cursor = rest;
+                mv_grid[gy * BLOCK_WRITTEN_WIDTH + gx] = mv;
+                macroblock.copy_to_yuv422_frame(MutableYUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, buffer), x, y);
+            }
+            other => panic!("Unrecognized macroblock mode byte {other}"),
+        }
+        decoded += 1;
+    }
+    decoded
+}
+
+/// Parsed `recv` command-line flags: `--record` opts into writing every consumed video packet to
+/// a capture file (see [`capture::CaptureWriter`]); `--replay <path>` re-feeds a previously
+/// recorded capture through the same decode/present path instead of listening live; `--record-video
+/// <path>` additionally saves the reconstructed pixels themselves to a Y4M file (see [`y4m::Y4mWriter`]).
+struct CliArgs {
+    record: bool,
+    replay_path: Option<String>,
+    record_video_path: Option<String>,
+}
+
+fn parse_args() -> CliArgs {
+    let mut args = CliArgs { record: false, replay_path: None, record_video_path: None };
+    let mut argv = std::env::args().skip(1);
+    while let Some(arg) = argv.next() {
+        match arg.as_str() {
+            "--record" => args.record = true,
+            "--replay" => {
+                args.replay_path = Some(argv.next().expect("--replay requires a file path"));
+            }
+            "--record-video" => {
+                args.record_video_path = Some(argv.next().expect("--record-video requires a file path"));
+            }
+            other => log::warn!("Ignoring unrecognized command-line argument: {other}"),
+        }
+    }
+    args
+}
+
+/// Completes the viewer's half of the handshake documented on [`kem`]: generates a fresh
+/// keypair, sends the [`kem::PublicKey`] to the streamer on `stream`, blocks until its
+/// [`kem::Ciphertext`] comes back, and derives the session key/salt
+/// [`transport::AuthenticatedEncryptedTransport`] needs from the recovered shared secret. Runs
+/// once, synchronously, before `main` sends any `ControlMessage` on the same socket, so the
+/// streamer always sees an already-keyed viewer.
+fn negotiate_session_key(stream: &UdpSocket) -> ([u8; chacha20::KEY_BYTES], transport::StreamSalt) {
+    let (pk, sk) = kem::generate_keypair();
+    stream.send(&pk.to_bytes()).unwrap();
+
+    let mut ct_bytes = vec![0u8; kem::CIPHERTEXT_BYTES];
+    stream.recv(&mut ct_bytes).unwrap();
+    let ciphertext = kem::Ciphertext::from_bytes(&ct_bytes);
+
+    let shared_secret = kem::decapsulate(&sk, &pk, &ciphertext);
+    kem::derive_session_key(&shared_secret)
+}
+
 fn main() -> std::io::Result<()> {
     run_louder::init_logger(false);
+    let cli_args = parse_args();
+
+    let mut video_recorder = if let Some(path) = &cli_args.record_video_path {
+        log::info!("Recording reconstructed video to {path}");
+        Some(y4m::Y4mWriter::create(path, VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, VIDEO_FPS_TARGET)?)
+    } else {
+        None
+    };
 
     let (bpf_write_channel, bpf_receive_channel) = std::sync::mpsc::channel();
     std::thread::spawn(move || {
@@ -40,8 +190,12 @@ fn main() -> std::io::Result<()> {
     sdl2::hint::set_video_minimize_on_focus_loss(false);
     let video_subsystem = sdl_context.video().unwrap();
 
-    // let audio_subsystem = sdl_context.audio().unwrap();
-    // let _audio = audio::play_audio(&audio_subsystem);
+    let audio_subsystem = sdl_context.audio().unwrap();
+    // `_audio_device` has to stay alive for the program's whole run -- dropping it stops
+    // playback -- so it's bound here and never touched again. `master_clock` is what actually
+    // gets used, to gate video presentation against the audio callback's playback position (see
+    // the main loop's `sync_video_frame` call below).
+    let (_audio_device, master_clock) = audio::play_audio(&audio_subsystem);
 
     let display_mode = video_subsystem.desktop_display_mode(0).unwrap();
 
@@ -65,17 +219,164 @@ fn main() -> std::io::Result<()> {
     let texture_creator = renderer.texture_creator();
     let mut texture = texture_creator.create_texture_streaming(PixelFormatEnum::YUY2, VIDEO_WIDTH, VIDEO_HEIGHT).unwrap();
 
-    let video_recieving_socket = udp_connect_retry((Ipv4Addr::UNSPECIFIED, RECV_VIDEO_PORT));
-    video_recieving_socket.connect((SEND_IP, SEND_VIDEO_PORT)).unwrap();
-    let video_reciever = rtp::RtpSlicePayloadReciever::<u8, PACKET_PAYLOAD_SIZE_THRESHOLD, 8192>::new(video_recieving_socket);
+    // One pixel per macroblock, visualizing each slot's skip/fill/coded decision (see
+    // `decode_video_packet_into_frame`'s `decision_grid` parameter) as a small picture-in-picture
+    // overlay, scaled up so it's actually visible next to the full-size video texture.
+    let mut decision_texture = texture_creator
+        .create_texture_streaming(PixelFormatEnum::RGB24, BLOCK_WRITTEN_WIDTH as u32, BLOCK_WRITTEN_HEIGHT as u32)
+        .unwrap();
+    const DECISION_OVERLAY_SCALE: u32 = 4;
+    let decision_dest_rect = Rect::new(
+        8,
+        8,
+        BLOCK_WRITTEN_WIDTH as u32 * DECISION_OVERLAY_SCALE,
+        BLOCK_WRITTEN_HEIGHT as u32 * DECISION_OVERLAY_SCALE,
+    );
+
+    // A second display buffer, trained and reconstructed locally from whatever this frame
+    // actually decoded to (not transmitted — there's no VQ wire format), so the demo can show the
+    // DCT path's ringing next to the VQ path's palette-starvation artifacts at the same WPM.
+    // Quarter-size, since it's a comparison thumbnail rather than the primary picture.
+    let mut vq_texture = texture_creator.create_texture_streaming(PixelFormatEnum::YUY2, VIDEO_WIDTH, VIDEO_HEIGHT).unwrap();
+    let vq_dest_rect = Rect::new(
+        (window_width - VIDEO_WIDTH / 4 - 8) as i32,
+        8,
+        VIDEO_WIDTH / 4,
+        VIDEO_HEIGHT / 4,
+    );
+
+    if let Some(replay_path) = &cli_args.replay_path {
+        let mut reader = capture::CaptureReader::open(replay_path)?;
+        let mut current_frame: Option<u32> = None;
+        let mut block_written = [[false; BLOCK_WRITTEN_WIDTH]; BLOCK_WRITTEN_HEIGHT];
+        // Replay doesn't render the decision overlay, but `decode_video_packet_into_frame` needs
+        // somewhere to record decisions into.
+        let mut decision_grid = [[0xFFu8; BLOCK_WRITTEN_WIDTH]; BLOCK_WRITTEN_HEIGHT];
+        // Replay has no live WPM/rate-controller feed to derive a `quality` from, so
+        // `reconstruct_frame` below just uses `wpm_to_jpeg_quality`'s value at 0 WPM.
+        let replay_quality = wpm::wpm_to_jpeg_quality(0.0);
+        // Mirrors `main`'s live-decode `mv_grid`/`previous_grid`: carried across frames so
+        // `video::decode_inter_macroblock` can reconstruct the same motion-compensated prediction
+        // the encoder used.
+        let mut mv_grid = vec![MotionVector::default(); BLOCK_WRITTEN_WIDTH * BLOCK_WRITTEN_HEIGHT];
+        let mut previous_grid = vec![Macroblock::default(); BLOCK_WRITTEN_WIDTH * BLOCK_WRITTEN_HEIGHT];
+        let mut previous_mv_grid = mv_grid.clone();
+
+        reader.replay_with_original_timing(|frame_count, data| {
+            if current_frame != Some(frame_count) {
+                if current_frame.is_some() {
+                    renderer.copy(&texture, None, dest_rect).unwrap();
+                    renderer.present();
+                }
+                log::info!("Replaying frame {frame_count}");
+                current_frame = Some(frame_count);
+                block_written = [[false; BLOCK_WRITTEN_WIDTH]; BLOCK_WRITTEN_HEIGHT];
+                // Snapshotted once per frame boundary, before this frame's packets start
+                // overwriting `texture`, the same as `main`'s live-decode loop.
+                previous_mv_grid = mv_grid.clone();
+                texture
+                    .with_lock(None, |buffer: &mut [u8], _pitch: usize| {
+                        // Deblock the just-finished previous frame before reading it back as this
+                        // frame's reference — see `video::deblock_frame`'s doc comment for why this
+                        // has to match `main`'s live-decode loop exactly.
+                        deblock_frame(&mut MutableYUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, buffer), replay_quality);
+                        let readback_frame = YUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, &*buffer);
+                        previous_grid = YUVFrameMacroblockIterator::new(&readback_frame)
+                            .map(|MacroblockWithPosition { block, .. }| block)
+                            .collect();
+                    })
+                    .unwrap();
+            }
+            texture
+                .with_lock(None, |buffer: &mut [u8], _pitch: usize| {
+                    decode_video_packet_into_frame(
+                        data,
+                        buffer,
+                        &mut block_written,
+                        &mut decision_grid,
+                        &previous_grid,
+                        &previous_mv_grid,
+                        &mut mv_grid,
+                    );
+
+                    if let Some(writer) = video_recorder.as_mut() {
+                        let readback_frame = YUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, &*buffer);
+                        let reconstructed = video::reconstruct_frame(&readback_frame, replay_quality);
+                        if let Err(e) = writer.write_frame(&reconstructed) {
+                            log::error!("Failed to write Y4M frame: {e}");
+                        }
+                    }
+                })
+                .unwrap();
+        })?;
+
+        // Present whatever the last recorded frame decoded to.
+        renderer.copy(&texture, None, dest_rect).unwrap();
+        renderer.present();
+        // The capture is exhausted here (`replay_with_original_timing` only returns once it's
+        // consumed every recorded packet), so finalize the Y4M file the same way `Quit` does.
+        if let Some(writer) = video_recorder.take() {
+            if let Err(e) = writer.finish() {
+                log::error!("Failed to finish Y4M recording: {e}");
+            }
+        }
+        return Ok(());
+    }
+
+    let mut capture_writer = if cli_args.record {
+        let path = capture::timestamped_capture_path("recv-capture");
+        log::info!("Recording consumed video packets to {}", path.display());
+        Some(capture::CaptureWriter::create(&path)?)
+    } else {
+        None
+    };
 
     let sender_communication_socket = udp_connect_retry((Ipv4Addr::UNSPECIFIED, RECV_CONTROL_PORT));
     sender_communication_socket.connect((SEND_IP, SEND_CONTROL_PORT)).unwrap();
 
     log::info!("Sender connected to control server from {:?}", sender_communication_socket.local_addr().unwrap());
 
+    // Keys the video socket below before any `ControlMessage` traffic goes out on
+    // `sender_communication_socket`, so the sender never sees anything but an already-keyed
+    // viewer.
+    let (session_key, session_salt) = negotiate_session_key(&sender_communication_socket);
+
+    let video_recieving_socket = udp_connect_retry((Ipv4Addr::UNSPECIFIED, RECV_VIDEO_PORT));
+    video_recieving_socket.connect((SEND_IP, SEND_VIDEO_PORT)).unwrap();
+    let video_reciever = rtp::RtpSlicePayloadReceiver::<u8, PACKET_PAYLOAD_SIZE_THRESHOLD, 8192>::with_transport(
+        transport::AuthenticatedEncryptedTransport::new(transport::UdpTransport::new(video_recieving_socket), session_key, session_salt),
+        VIDEO_SSRC,
+    );
+
+    // Minimum spacing between two refresh requests, so a stretch of sustained loss keeps nagging
+    // the sender for a fixed region rather than re-requesting (and re-triggering a re-encode of)
+    // the same still-missing macroblocks every single frame.
+    const REFRESH_REQUEST_MIN_INTERVAL: Duration = Duration::from_millis(200);
+    // Above this fraction of missing macroblocks, ask for a full-frame refresh instead of
+    // localizing the damage — past this point the per-block bounding box likely covers most of
+    // the frame anyway, so a full refresh is no more expensive and recovers faster.
+    const FULL_REFRESH_LOSS_FRACTION: f64 = 0.3;
+    let mut last_refresh_request_sent = Instant::now() - REFRESH_REQUEST_MIN_INTERVAL;
+
     let mut frame_count = 0;
     let mut typing_metrics = wpm::TypingMetrics::new();
+    let mut receiver_stats = stats::ReceiverStats::new();
+    // Minimum spacing between stats summary lines, so the dashboard log line doesn't spam at
+    // frame rate.
+    const STATS_LOG_INTERVAL: Duration = Duration::from_secs(1);
+    let mut last_stats_log = Instant::now() - STATS_LOG_INTERVAL;
+    // Drives `quality` toward a target bits-per-frame budget instead of `wpm` mapping to it
+    // directly, using each frame's actual entropy-coded size (see `video::encode_frame` below) as
+    // feedback.
+    let mut rate_controller = ratecontrol::RateController::new(
+        ratecontrol::wpm_to_target_bits(0.0),
+        ratecontrol::QUALITY_BOUNDS,
+    );
+    // Each macroblock's most recently decoded motion vector, so `video::decode_inter_macroblock`
+    // can reconstruct the same predictor `send.rs`'s encoder used (see
+    // `video::predicted_motion_vector`) — carried across frames the same way `send.rs`'s own
+    // `previous_mv_grid` is.
+    let mut mv_grid = vec![MotionVector::default(); BLOCK_WRITTEN_WIDTH * BLOCK_WRITTEN_HEIGHT];
     loop {
         let start_time = std::time::Instant::now();
 
@@ -84,7 +385,14 @@ fn main() -> std::io::Result<()> {
         let mut event_pump = sdl_context.event_pump().unwrap();
         for event in event_pump.poll_iter() {
             match event {
-                sdl2::event::Event::Quit {..} => return Ok(()),
+                sdl2::event::Event::Quit {..} => {
+                    if let Some(writer) = video_recorder.take() {
+                        if let Err(e) = writer.finish() {
+                            log::error!("Failed to finish Y4M recording: {e}");
+                        }
+                    }
+                    return Ok(());
+                },
                 sdl2::event::Event::KeyDown { keycode, repeat: false, timestamp: _, .. } => {
                     match keycode {
                         Some(k) => {
@@ -101,6 +409,14 @@ fn main() -> std::io::Result<()> {
         let wpm = typing_metrics.calc_wpm();
         log::info!("WPM: {}", wpm);
 
+        // Computed up front (rather than just below, alongside the control message) so the
+        // entropy-coding instrumentation further down in this same loop iteration can use it too.
+        // `quality` itself now comes from `rate_controller`, which converges on a target bitrate
+        // rather than mapping WPM to `quality` directly; WPM only scales the target it converges
+        // toward (see `ratecontrol::wpm_to_target_bits`).
+        rate_controller.set_target_bits(ratecontrol::wpm_to_target_bits(wpm));
+        let quality = rate_controller.quality();
+
         let bpf_drop_rate = wpm::wpm_to_drop_amt(wpm);
         log::info!("BPF drop rate: {} ({})", bpf_drop_rate, (bpf_drop_rate as f64 / u32::MAX as f64) * 100.0);
 
@@ -112,33 +428,63 @@ fn main() -> std::io::Result<()> {
         }
 
         // send desired quality to sender
-        let quality = wpm::wpm_to_jpeg_quality(wpm);
-        let control_msg = ControlMessage { quality };
+        let audio_quantization_shift = wpm::wpm_to_audio_quantization_shift(wpm);
+        let control_msg = ControlMessage {
+            quality,
+            audio_quantization_shift,
+            refresh_request: RefreshRequest::default(),
+            mtu_report: MtuReport { usable_payload_bytes: 0 },
+            fec_group_size: 0,
+            wpm,
+        };
         udp_send_retry(&sender_communication_socket, control_msg.as_bytes());
-        log::debug!("Sent quality update: {}", quality);
+        log::debug!("Sent quality update: {} (audio shift {})", quality, audio_quantization_shift);
 
         // Draw video
 
         renderer.set_draw_color(wpm::wpm_to_sdl_color(wpm, Color::GREEN));
         renderer.clear();
 
-        texture.with_lock(None, |buffer: &mut [u8], _pitch: usize| {            
-            let mut locked_video_reciever = video_reciever.lock_reciever();
+        let frame_decisions = texture.with_lock(None, |buffer: &mut [u8], _pitch: usize| {
+            let mut locked_video_reciever = video_reciever.lock_receiver();
 
-            // If the circular buffer hasn't seen enough future packets, wait for more to arrive
+            // If the circular buffer hasn't seen enough future packets, wait for more to arrive.
+            // The target depth adapts to observed jitter instead of a fixed span, so a clean link
+            // doesn't pay for latency a bursty one would actually need.
             // Handles the case: sender is falling behind in sending packets.
-            if locked_video_reciever.early_latest_span() < 20 {
-                log::info!("Sleeping and waiting for more packets to arrive. Early-latest span {}", locked_video_reciever.early_latest_span());
-                return;
+            let target_span = locked_video_reciever.target_buffer_span(1000.0 / VIDEO_FPS_TARGET);
+            if locked_video_reciever.early_latest_span() < target_span {
+                log::info!(
+                    "Sleeping and waiting for more packets to arrive. Early-latest span {} (target {target_span})",
+                    locked_video_reciever.early_latest_span()
+                );
+                return (None, None);
             }
 
             log::info!("Playing frame {}", frame_count);
 
-            const BLOCK_WRITTEN_WIDTH: usize = (VIDEO_WIDTH as usize) / MACROBLOCK_X_DIM;
-            const BLOCK_WRITTEN_HEIGHT: usize = (VIDEO_HEIGHT as usize) / MACROBLOCK_Y_DIM;
-
             let mut block_written = [[false; BLOCK_WRITTEN_WIDTH]; BLOCK_WRITTEN_HEIGHT];
-            
+            let mut decision_grid = [[0xFFu8; BLOCK_WRITTEN_WIDTH]; BLOCK_WRITTEN_HEIGHT];
+
+            // Snapshotted before any of this frame's packets are decoded into `buffer` below, so
+            // a `MODE_CODED` macroblock's motion compensation (see `video::decode_inter_macroblock`)
+            // reconstructs against the previous frame, not whatever partial state this frame has
+            // already written.
+            let previous_grid: Vec<_> = {
+                let readback_frame = YUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, &*buffer);
+                YUVFrameMacroblockIterator::new(&readback_frame)
+                    .map(|MacroblockWithPosition { block, .. }| block)
+                    .collect()
+            };
+            let previous_mv_grid = mv_grid.clone();
+
+            // Same `rtp_epoch`-relative millisecond domain as audio's presentation timestamps
+            // (see `audio::send_audio`/`AudioCallbackData::callback`), so `master_clock` can
+            // compare the two directly in `sync_video_frame` below.
+            let frame_pts_millis = locked_video_reciever
+                .peek_earliest_packet()
+                .map(|p| p.header.timestamp.get() as u64);
+
             let mut packet_index = 0usize;
             while (packet_index as u32) < (VIDEO_HEIGHT * VIDEO_WIDTH * PIXEL_WIDTH as u32 / MACROBLOCK_BYTE_SIZE as u32) {
                 // if we have a packet with a higher frame number, earlier packets have been dropped from the circular buffer
@@ -151,61 +497,231 @@ fn main() -> std::io::Result<()> {
                     let packet_frame_count = cursor.get_u32();
                     if packet_frame_count > frame_count {
                         log::warn!("Skipping ahead to frame {}", packet_frame_count);
+                        receiver_stats.record_frame_skipped();
                         frame_count = packet_frame_count;
                         packet_index = 0;
                     }
                 }
 
                 let packet = locked_video_reciever.consume_earliest_packet();
-                if let Some(packet) = packet.get_data() {
-                    // copy the packet data into the buffer
-                    let mut cursor = &packet.data[..];
-                    log::trace!("Packet slice has length {}", cursor.len());
-
-                    let cursor_start_len = cursor.len();
-                    let _packet_frame_count = cursor.get_u32();
-                    loop {
-                        let cursor_position = cursor_start_len - cursor.remaining();
-                        let x = cursor.get_u16() as usize;
-                        let y = cursor.get_u16() as usize;
-                        
-                        if (x == u16::MAX as usize) && (y == u16::MAX as usize) {
+                if let Some(packet) = packet.as_ref().and_then(|p| p.get_data()) {
+                    log::trace!("Packet slice has length {}", packet.data.len());
+
+                    if let Some(writer) = capture_writer.as_mut() {
+                        if let Err(e) = writer.write_packet(frame_count, &packet.data) {
+                            log::error!("Failed to write capture record: {e}");
+                        }
+                    }
+
+                    packet_index += decode_video_packet_into_frame(
+                        &packet.data[..],
+                        buffer,
+                        &mut block_written,
+                        &mut decision_grid,
+                        &previous_grid,
+                        &previous_mv_grid,
+                        &mut mv_grid,
+                    );
+                }
+                else {
+                    match locked_video_reciever.diagnose_gap() {
+                        rtp::PlayoutGap::Lost => {
+                            // A true loss, not just earliness: the missing macroblocks stay
+                            // concealed for free, since `buffer` still holds whatever the
+                            // previous frame decoded into those same pixels (`with_lock` never
+                            // clears it) — so concealment here is just skipping the dead slot
+                            // and moving on instead of stalling the frame on it.
+                            locked_video_reciever.skip_lost_packet();
+                            // Still an estimate, not an exact macroblock count: we don't know
+                            // how many macroblocks the lost packet would have carried.
+                            packet_index += 40;
+                        }
+                        rtp::PlayoutGap::NotReady => {
+                            // Not lost, just not here yet within the playout delay — stop for
+                            // this frame rather than pretend progress was made.
                             break;
                         }
-                        let quality = cursor.get_f64();
+                    }
+                }
+            }
 
-                        block_written[y / MACROBLOCK_Y_DIM][x / MACROBLOCK_X_DIM] = true;
+            // Smooth over the 16x16 macroblock-edge blocking this frame's independently-quantized
+            // macroblocks leave behind, before `buffer` becomes next frame's `previous_grid` — run
+            // through the exact same `video::deblock_frame` pass `send.rs` runs on its own
+            // locally-reconstructed reference, so the two sides' idea of this frame never diverges
+            // (see `video::deblock_frame`'s doc comment).
+            deblock_frame(&mut MutableYUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, buffer), quality);
+
+            frame_count += 1;
 
-                        // log::trace!("Receiving MacroblockWithPos at ({frame_count}, {x}, {y}) at cursor position {cursor_position}");
+            // Paint this frame's skip/fill/coded decisions into the debug overlay texture, one
+            // pixel per macroblock.
+            decision_texture
+                .with_lock(None, |overlay_buf: &mut [u8], overlay_pitch: usize| {
+                    for (by, row) in decision_grid.iter().enumerate() {
+                        for (bx, &mode) in row.iter().enumerate() {
+                            let (r, g, b) = decision_mode_color(mode);
+                            let offset = by * overlay_pitch + bx * 3;
+                            overlay_buf[offset] = r;
+                            overlay_buf[offset + 1] = g;
+                            overlay_buf[offset + 2] = b;
+                        }
+                    }
+                })
+                .unwrap();
+
+            // Measure what this frame would actually cost as a real entropy-coded bitstream
+            // (DC-delta + zero-run AC coding, canonical-Huffman-coded — see `video::encode_frame`),
+            // and feed that back into `rate_controller` so the *next* frame's `quality` converges
+            // on the target bitrate instead of `quality` being a pure function of WPM.
+            {
+                let readback_frame = YUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, &*buffer);
+                let encoded = video::encode_frame(&readback_frame, quality);
+                let actual_bits = encoded.len() as f64 * 8.0;
+                let bitrate_kbps = actual_bits * VIDEO_FPS_TARGET / 1000.0;
+                log::info!("Entropy-coded frame size: {} bytes ({:.1} kbps)", encoded.len(), bitrate_kbps);
+                rate_controller.update(actual_bits);
+            }
 
-                        let decoded_quantized_macroblock;
-                        (decoded_quantized_macroblock, cursor) = decode_quantized_macroblock(&cursor);
-                        let macroblock = dequantize_macroblock(&decoded_quantized_macroblock, quality);
-                        macroblock.copy_to_yuv422_frame(MutableYUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, buffer), x, y);
-                        packet_index += 1;
+            // Train a codebook on this frame's own macroblocks and round-trip them through it, so
+            // the VQ overlay shows what the alternative codec path would have reconstructed at the
+            // same WPM — comparison only, since there's no VQ wire format to actually transmit.
+            let codebook = {
+                let readback_frame = YUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, &*buffer);
+                let blocks: Vec<_> = YUVFrameMacroblockIterator::new(&readback_frame)
+                    .map(|MacroblockWithPosition { block, .. }| block)
+                    .collect();
+                build_codebook(&blocks, wpm::wpm_to_vq_codebook_size(wpm))
+            };
+            vq_texture
+                .with_lock(None, |vq_buf: &mut [u8], _vq_pitch: usize| {
+                    let readback_frame = YUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, &*buffer);
+                    let positions: Vec<_> = YUVFrameMacroblockIterator::new(&readback_frame).collect();
+                    for MacroblockWithPosition { block, x, y } in positions {
+                        let indices = encode_block(&codebook, &block);
+                        let reconstructed = decode_block(&codebook, &indices);
+                        reconstructed.copy_to_yuv422_frame(MutableYUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, vq_buf), x, y);
                     }
+                })
+                .unwrap();
+
+            // Save the reconstructed pixels themselves (distinct from `capture_writer`'s
+            // coded-packet recording, which replays the coded macroblock stream through the
+            // decoder again) to the Y4M file, if `--record-video` was passed. Shares
+            // `reconstruct_frame` with nothing else in this closure — the on-screen `texture` is
+            // already the live per-macroblock RTP decode, not the full-frame round-trip.
+            if let Some(writer) = video_recorder.as_mut() {
+                let readback_frame = YUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, &*buffer);
+                let reconstructed = video::reconstruct_frame(&readback_frame, quality);
+                if let Err(e) = writer.write_frame(&reconstructed) {
+                    log::error!("Failed to write Y4M frame: {e}");
                 }
-                else {
-                    // TODO: fix this hack
-                    // roughly 40 macroblocks per packet are packed in
-                    packet_index += 40;
+            }
+
+            // Scan for macroblock positions that never arrived this frame, so loss can be
+            // reported back to the sender (see below) instead of silently leaving a hole that
+            // persists until that position happens to be touched again.
+            let mut missing_count = 0usize;
+            let (mut min_x, mut min_y) = (BLOCK_WRITTEN_WIDTH, BLOCK_WRITTEN_HEIGHT);
+            let (mut max_x, mut max_y) = (0usize, 0usize);
+            for (by, row) in block_written.iter().enumerate() {
+                for (bx, &written) in row.iter().enumerate() {
+                    if !written {
+                        missing_count += 1;
+                        min_x = min_x.min(bx);
+                        min_y = min_y.min(by);
+                        max_x = max_x.max(bx + 1);
+                        max_y = max_y.max(by + 1);
+                    }
                 }
             }
 
-            frame_count += 1;
+            receiver_stats.record_macroblocks_dropped(missing_count, BLOCK_WRITTEN_WIDTH * BLOCK_WRITTEN_HEIGHT);
+
+            let refresh_request = if missing_count == 0 {
+                None
+            } else if missing_count as f64 / (BLOCK_WRITTEN_WIDTH * BLOCK_WRITTEN_HEIGHT) as f64 > FULL_REFRESH_LOSS_FRACTION {
+                // Localizing the damage isn't worth it anymore; ask for the whole frame (an empty
+                // range means "everything" to `refresh_region_from_request`).
+                Some(RefreshRequest { requested: 1, ..Default::default() })
+            } else {
+                Some(RefreshRequest {
+                    requested: 1,
+                    x_start: min_x as u16,
+                    y_start: min_y as u16,
+                    x_end: max_x as u16,
+                    y_end: max_y as u16,
+                    ..Default::default()
+                })
+            };
+
+            // No pts for this frame (buffer still waiting on its first packet) leaves nothing to
+            // sync against; present it unconditionally rather than stalling forever.
+            let sync_decision = frame_pts_millis.map(|pts| master_clock.sync_video_frame(pts));
+
+            (refresh_request, sync_decision)
         }).unwrap();
+        let (refresh_request, sync_decision) = frame_decisions;
+
+        if let Some(refresh_request) = refresh_request {
+            if last_refresh_request_sent.elapsed() >= REFRESH_REQUEST_MIN_INTERVAL {
+                last_refresh_request_sent = Instant::now();
+                let control_msg = ControlMessage {
+                    quality,
+                    audio_quantization_shift,
+                    refresh_request,
+                    mtu_report: MtuReport { usable_payload_bytes: 0 },
+                    fec_group_size: 0,
+                    wpm,
+                };
+                udp_send_retry(&sender_communication_socket, control_msg.as_bytes());
+                log::debug!(
+                    "Requested refresh for ({}, {})..({}, {})",
+                    refresh_request.x_start, refresh_request.y_start, refresh_request.x_end, refresh_request.y_end
+                );
+            }
+        }
 
-        renderer.copy(&texture, None, dest_rect).unwrap();
-        renderer.present();
+        // ffplay-style A/V sync: a frame running ahead of the audio callback's playback position
+        // waits before showing, one far enough behind is dropped outright rather than shown
+        // stale, and one within `audio::AV_SYNC_THRESHOLD_MILLIS` shows immediately. See
+        // `audio::MasterClock::sync_video_frame`.
+        let should_present = match sync_decision {
+            Some(audio::SyncDecision::Drop) => {
+                receiver_stats.record_frame_skipped();
+                false
+            }
+            Some(audio::SyncDecision::Delay(millis)) => {
+                std::thread::sleep(Duration::from_millis(millis));
+                true
+            }
+            Some(audio::SyncDecision::Present) | None => true,
+        };
+
+        if should_present {
+            renderer.copy(&texture, None, dest_rect).unwrap();
+            renderer.copy(&decision_texture, None, decision_dest_rect).unwrap();
+            renderer.copy(&vq_texture, None, vq_dest_rect).unwrap();
+            renderer.present();
+        }
 
         let elapsed = start_time.elapsed();
         log::info!("Recieved and drew frame {} in {} ms", frame_count, elapsed.as_millis());
         // delay to hit target FPS
         let target_latency = Duration::from_secs_f64(1.0 / VIDEO_FPS_TARGET);
-        if elapsed < target_latency {
-            std::thread::sleep(target_latency - elapsed);
+        let overshoot = elapsed.checked_sub(target_latency);
+        if let Some(overshoot) = overshoot {
+            log::warn!("Receiver took too long presenting; overshot frame deadline by {} ms", overshoot.as_millis());
         } else {
-            log::warn!("Receiver took too long presenting; overshot frame deadline by {} ms", (elapsed - target_latency).as_millis());
+            std::thread::sleep(target_latency - elapsed);
+        }
+        if should_present {
+            receiver_stats.record_frame_presented(elapsed, overshoot);
+        }
+
+        if last_stats_log.elapsed() >= STATS_LOG_INTERVAL {
+            last_stats_log = Instant::now();
+            log::info!("receiver stats: {}", receiver_stats.summary());
         }
     }
 }
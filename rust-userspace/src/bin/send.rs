@@ -8,8 +8,8 @@
 
 use rayon::iter::ParallelBridge;
 use rayon::iter::ParallelIterator;
-use rtp::RtpSlicePayloadSender;
 use rust_userspace::*;
+use transport::{AuthenticatedEncryptedTransport, Transport, UdpTransport};
 
 use bytes::BufMut;
 use std::convert::Infallible;
@@ -18,9 +18,10 @@ use std::net::UdpSocket;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::RwLock;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use video::{
-    encode_quantized_macroblock, quantize_macroblock, MacroblockWithPosition, YUVFrame,
+    deblock_frame, encode_frame_macroblock, refresh_region_from_request, Macroblock,
+    MacroblockDecision, MacroblockWithPosition, MotionVector, MutableYUVFrame, YUVFrame,
     YUVFrameMacroblockIterator,
 };
 use zerocopy::FromBytes;
@@ -32,7 +33,39 @@ fn main() -> std::io::Result<()> {
     Ok(())
 }
 
-fn receive_control(quality: Arc<RwLock<f64>>, stream: UdpSocket) {
+/// Minimum spacing between two NACK-triggered out-of-band resends, so a receiver re-requesting
+/// the same still-missing macroblocks every frame (see `recv`'s own rate limiter) can't make the
+/// sender redo the same targeted re-encode faster than it's useful to.
+const REFRESH_REQUEST_MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Completes the streamer's half of the handshake documented on [`kem`]: blocks until the
+/// viewer's [`kem::PublicKey`] arrives on `stream`, encapsulates a fresh shared secret under it,
+/// sends the resulting [`kem::Ciphertext`] back, and derives the session key/salt
+/// [`AuthenticatedEncryptedTransport`] needs from the shared secret. Runs once, synchronously,
+/// before `send_video` hands `stream` off to [`receive_control`] for the ongoing quality/refresh
+/// traffic, so the video socket below it is never constructed with a cleartext [`Transport`].
+fn negotiate_session_key(stream: &UdpSocket) -> ([u8; chacha20::KEY_BYTES], transport::StreamSalt) {
+    let mut pk_bytes = vec![0u8; kem::PUBLIC_KEY_BYTES];
+    stream.recv(&mut pk_bytes).unwrap();
+    let pk = kem::PublicKey::from_bytes(&pk_bytes);
+
+    let (ciphertext, shared_secret) = kem::encapsulate(&pk);
+    stream.send(&ciphertext.to_bytes()).unwrap();
+
+    kem::derive_session_key(&shared_secret)
+}
+
+/// Parses incoming `ControlMessage`s and updates the shared state `send_video`'s main loop acts
+/// on: it only records the latest quality and refresh request rather than acting on the refresh
+/// request itself, since doing that requires the camera's most recent frame and the RTP sender —
+/// both of which live on the main loop's stack, not this thread's.
+fn receive_control(
+    quality: Arc<RwLock<f64>>,
+    audio_quantization_shift: Arc<RwLock<u32>>,
+    pending_refresh: Arc<Mutex<RefreshRequest>>,
+    wpm: Arc<RwLock<f64>>,
+    stream: UdpSocket,
+) {
     let mut msg_buf = [0; size_of::<ControlMessage>()];
     log::info!("Listening for control server!");
     loop {
@@ -40,6 +73,259 @@ fn receive_control(quality: Arc<RwLock<f64>>, stream: UdpSocket) {
         let control_msg = ControlMessage::ref_from_bytes(&msg_buf).unwrap();
         log::debug!("Received quality update: {}", control_msg.quality);
         *quality.write().unwrap() = control_msg.quality;
+        *audio_quantization_shift.write().unwrap() = control_msg.audio_quantization_shift;
+        *wpm.write().unwrap() = control_msg.wpm;
+
+        if control_msg.refresh_request.requested != 0 {
+            *pending_refresh.lock().unwrap() = control_msg.refresh_request;
+        }
+    }
+}
+
+/// How a run of macroblocks gets grouped into outgoing packets, mirroring the MPEG-4 Generic RTP
+/// payloader's `aggregate-mode` option (RFC 3640 3.2.1): pack several access units per packet to
+/// save header/framing overhead, or send each on its own to cut packing latency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AggregationMode {
+    /// Flush as soon as one macroblock's coded data is appended, so a macroblock is never held
+    /// back waiting for others to join it in the same packet — lowest latency, worst packing
+    /// efficiency.
+    LowLatency,
+    /// Keep appending macroblocks until the packet is within [`PAYLOAD_FULL_WATERMARK`] of
+    /// `PACKET_PAYLOAD_SIZE_THRESHOLD` or [`MAX_MACROBLOCKS_PER_PACKET`] have been packed — fewest
+    /// packets, most packing latency.
+    Fill,
+    /// `LowLatency` once the sender's current WPM-derived quality has dropped at or below
+    /// [`AUTO_AGGREGATION_QUALITY_THRESHOLD`] (encoded macroblocks are already small and sparse at
+    /// low quality, so there's little overhead to amortize and the packing delay shows more),
+    /// `Fill` otherwise.
+    Auto,
+}
+
+/// Active aggregation policy for `send_video`'s video RTP stream. See [`AggregationMode`].
+const PACKET_AGGREGATION: AggregationMode = AggregationMode::Fill;
+
+/// Quality (see `wpm::wpm_to_jpeg_quality`; lower means coarser quantization) at or below which
+/// [`AggregationMode::Auto`] switches from [`AggregationMode::Fill`] to [`AggregationMode::LowLatency`].
+const AUTO_AGGREGATION_QUALITY_THRESHOLD: f64 = 0.15;
+
+/// Hard ceiling on macroblocks packed into one packet, independent of the payload-size watermark —
+/// keeps a pathological case (many tiny encoded macroblocks at very low quality) from packing so
+/// many into a single packet that one dropped packet costs an outsized chunk of the frame.
+const MAX_MACROBLOCKS_PER_PACKET: usize = 32;
+
+/// Fraction of `PACKET_PAYLOAD_SIZE_THRESHOLD` a packet must reach before [`AggregationMode::Fill`]
+/// flushes it, leaving headroom for whatever `PACKET_FRAMING` needs to close out the packet (the
+/// `Legacy` sentinel, or nothing for `Standard`).
+const PAYLOAD_FULL_WATERMARK: f64 = 0.95;
+
+/// Resolves [`AggregationMode::Auto`] against the sender's current quality; other modes pass
+/// through unchanged.
+fn resolve_aggregation_mode(quality: f64) -> AggregationMode {
+    match PACKET_AGGREGATION {
+        AggregationMode::Auto if quality <= AUTO_AGGREGATION_QUALITY_THRESHOLD => AggregationMode::LowLatency,
+        AggregationMode::Auto => AggregationMode::Fill,
+        mode => mode,
+    }
+}
+
+/// A packet payload under construction: the raw bytes (already carrying the `frame_count: u32`
+/// prefix) plus how many macroblocks have been packed into it, since [`AggregationMode`] needs the
+/// count as well as the byte length to decide when to flush.
+struct PacketAccumulator {
+    buf: Vec<u8>,
+    macroblock_count: usize,
+}
+
+impl PacketAccumulator {
+    fn new(frame_count: u32) -> Self {
+        let mut buf = Vec::with_capacity(PACKET_PAYLOAD_SIZE_THRESHOLD);
+        buf.put_u32(frame_count);
+        PacketAccumulator { buf, macroblock_count: 0 }
+    }
+}
+
+/// Encodes and sends every macroblock in `[x, x_end) x [y, y_end)`, splitting the output across
+/// as many packets as `PACKET_PAYLOAD_SIZE_THRESHOLD` (and `PACKET_AGGREGATION`) require. Shared
+/// by `send_video`'s regular per-frame sweep and its NACK-triggered targeted resend.
+fn process_block<Tr: Transport>(
+    quality: Arc<RwLock<f64>>,
+    skip_threshold: u32,
+    fill_threshold: u32,
+    previous_frame: Arc<Mutex<Vec<Macroblock>>>,
+    previous_mv_grid: Arc<Mutex<Vec<MotionVector>>>,
+    frame: &YUVFrame<'_>,
+    frame_count: u32,
+    frame_timestamp: u32,
+    x: usize,
+    y: usize,
+    x_end: usize,
+    y_end: usize,
+    sender: Arc<Mutex<&mut rtp::RtpSender<[u8], u8, PACKET_PAYLOAD_SIZE_THRESHOLD, Tr>>>,
+    packet_buf: Arc<Mutex<PacketAccumulator>>,
+) {
+    let mut current_macroblock_buf = Vec::with_capacity(PACKET_PAYLOAD_SIZE_THRESHOLD);
+    // Reserve room for whatever `PACKET_FRAMING` needs to close out a packet: the
+    // `Legacy` sentinel, or nothing for `Standard`, which has no end-of-packet marker.
+    let packet_close_reserve = match PACKET_FRAMING {
+        PacketFraming::Legacy => 2 * size_of::<u16>(),
+        PacketFraming::Standard => 0,
+    };
+
+    // Snapshotted once per call rather than per-macroblock: motion search (see
+    // `video::encode_frame_macroblock`) reaches outside its own grid cell into neighboring
+    // macroblocks, which may belong to a different `process_block` call (or a different rayon
+    // task within this same call) than the one writing them back below — a frozen start-of-call
+    // view is simplest and good enough, matching this codec's existing tolerance for `previous_frame`
+    // being read and written concurrently across the frame.
+    let reference_grid = previous_frame.lock().unwrap().clone();
+    let reference_mv_grid = previous_mv_grid.lock().unwrap().clone();
+
+    for MacroblockWithPosition { x, y, block } in
+        YUVFrameMacroblockIterator::new_with_bounds(frame, x, y, x_end, y_end)
+    {
+        current_macroblock_buf.clear();
+
+        // latest quality, as last set from the receiver's control feedback
+        let quality = quality.read().unwrap().clone();
+
+        let gx = x / MACROBLOCK_X_DIM;
+        let gy = y / MACROBLOCK_Y_DIM;
+        let grid_index = gy * BLOCK_GRID_WIDTH + gx;
+
+        let mut payload = Vec::new();
+        let (decision, reconstructed, mv, used_quality) = encode_frame_macroblock(
+            &block,
+            &reference_grid,
+            &reference_mv_grid,
+            BLOCK_GRID_WIDTH,
+            BLOCK_GRID_HEIGHT,
+            gx,
+            gy,
+            skip_threshold,
+            fill_threshold,
+            quality,
+            &mut payload,
+        );
+        previous_frame.lock().unwrap()[grid_index] = reconstructed;
+        previous_mv_grid.lock().unwrap()[grid_index] = mv;
+
+        match PACKET_FRAMING {
+            PacketFraming::Legacy => {
+                current_macroblock_buf.put_u16(x as u16);
+                current_macroblock_buf.put_u16(y as u16);
+                current_macroblock_buf.put_u8(macroblock_decision_mode_byte(decision));
+                if decision == MacroblockDecision::Coded {
+                    current_macroblock_buf.put_f64(used_quality);
+                }
+                current_macroblock_buf.put_slice(&payload);
+            }
+            PacketFraming::Standard => {
+                current_macroblock_buf.put_u16(x as u16);
+                current_macroblock_buf.put_u16(y as u16);
+                current_macroblock_buf.put_u8(macroblock_decision_mode_byte(decision));
+                if decision == MacroblockDecision::Coded {
+                    current_macroblock_buf.put_f64(used_quality);
+                }
+                current_macroblock_buf.put_u16(payload.len() as u16);
+                current_macroblock_buf.put_slice(&payload);
+            }
+        }
+
+        let mut packet_buf = packet_buf.lock().unwrap();
+        let would_overflow = packet_buf.buf.len() + current_macroblock_buf.len() + packet_close_reserve
+            >= PACKET_PAYLOAD_SIZE_THRESHOLD;
+        let watermark_reached = packet_buf.buf.len() as f64
+            >= PACKET_PAYLOAD_SIZE_THRESHOLD as f64 * PAYLOAD_FULL_WATERMARK;
+        let should_flush = packet_buf.macroblock_count > 0
+            && (would_overflow
+                || match resolve_aggregation_mode(quality) {
+                    AggregationMode::LowLatency => true,
+                    AggregationMode::Fill => {
+                        watermark_reached || packet_buf.macroblock_count >= MAX_MACROBLOCKS_PER_PACKET
+                    }
+                    AggregationMode::Auto => unreachable!("resolve_aggregation_mode never returns Auto"),
+                });
+        if should_flush {
+            // send the packet (not the frame's last one, so no marker) and start a new one
+            match PACKET_FRAMING {
+                PacketFraming::Legacy => {
+                    packet_buf.buf.put_u16(u16::MAX);
+                    packet_buf.buf.put_u16(u16::MAX);
+                    sender.lock().unwrap().send_bytes(frame_timestamp, false, |mem| {
+                        mem[..packet_buf.buf.len()].copy_from_slice(&packet_buf.buf);
+                        packet_buf.buf.len()
+                    });
+                }
+                PacketFraming::Standard => {
+                    sender.lock().unwrap().send_bytes(frame_timestamp, false, |mem| {
+                        mem[..packet_buf.buf.len()].copy_from_slice(&packet_buf.buf);
+                        packet_buf.buf.len()
+                    });
+                }
+            }
+            *packet_buf = PacketAccumulator::new(frame_count);
+        }
+
+        // The macroblock consists of x, y, and the encoded macroblock
+        // log::trace!(
+        //     "Storing macroblock at ({}, {}, {}) at cursor position {}",
+        //     frame_count,
+        //     x,
+        //     y,
+        //     packet_buf.buf.len()
+        // );
+        packet_buf.buf.put_slice(&current_macroblock_buf);
+        packet_buf.macroblock_count += 1;
+    }
+}
+
+/// Wire layout used to packetize macroblocks into the video RTP stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PacketFraming {
+    /// This codec's original ad hoc layout: `frame_count: u32`, then repeated
+    /// `(x: u16, y: u16, mode: u8, [quality: f64], mode-specific payload)` tuples with no length
+    /// prefix, terminated by an `(x, y) == (u16::MAX, u16::MAX)` sentinel. Opaque to anything that
+    /// doesn't already know this exact layout; kept around to A/B benchmark against `Standard`.
+    Legacy,
+    /// A layout documented well enough for a third-party depayloader to reconstruct frames from,
+    /// on the model of RFC-style uncompressed/tiled video payloads: `frame_count: u32`, then
+    /// repeated length-prefixed macroblock descriptors read until the packet is exhausted (no
+    /// sentinel needed):
+    ///
+    /// ```text
+    /// x: u16          macroblock-grid column, in pixels
+    /// y: u16          macroblock-grid row, in pixels
+    /// mode: u8        see video::MacroblockDecision: 0 = Coded, 1 = Fill, 2 = Skip
+    /// quality: f64    quantization quality this macroblock was coded at; present only if mode == 0
+    /// len: u16        byte length of the mode-specific payload that follows
+    /// data: [u8; len] Coded: entropy-coded macroblock (see `video::encode_quantized_macroblock`);
+    ///                 Fill: averaged (luma, u, v) triple; Skip: empty
+    /// ```
+    ///
+    /// The frame's last packet is flagged via [`rtp::RtpSender::send_bytes`]'s marker bit instead
+    /// of being inferred from frame-number bookkeeping, mirroring how marker bits flag a frame
+    /// boundary in standard RTP video payloads (e.g. RFC 4175).
+    Standard,
+}
+
+/// Active framing mode for `send_video`'s video RTP stream.
+const PACKET_FRAMING: PacketFraming = PacketFraming::Legacy;
+
+/// Macroblock-grid dimensions (in macroblock units, not pixels), mirroring `recv.rs`'s
+/// `BLOCK_WRITTEN_WIDTH`/`BLOCK_WRITTEN_HEIGHT` — the shape of `send_video`'s `previous_frame`
+/// buffer that [`encode_frame_macroblock`] codes each macroblock against.
+const BLOCK_GRID_WIDTH: usize = VIDEO_WIDTH as usize / MACROBLOCK_X_DIM;
+const BLOCK_GRID_HEIGHT: usize = VIDEO_HEIGHT as usize / MACROBLOCK_Y_DIM;
+
+/// Maps a [`MacroblockDecision`] to the `mode: u8` byte written onto the wire (see
+/// [`PacketFraming`]), so the receiver can tell Coded/Fill/Skip apart without guessing from
+/// payload length alone.
+fn macroblock_decision_mode_byte(decision: MacroblockDecision) -> u8 {
+    match decision {
+        MacroblockDecision::Coded => 0,
+        MacroblockDecision::Fill => 1,
+        MacroblockDecision::Skip => 2,
     }
 }
 
@@ -149,13 +435,61 @@ pub fn send_video() {
         .connect((RECV_IP, RECV_CONTROL_PORT))
         .unwrap();
 
+    // Key the video socket before handing `receiver_communication_socket` off to
+    // `receive_control` for its ongoing traffic, so every video packet that follows is actually
+    // encrypted rather than riding in cleartext over `sock`.
+    let (session_key, session_salt) = negotiate_session_key(&receiver_communication_socket);
+
     let quality = Arc::new(RwLock::new(0.3));
     let cloned_quality = quality.clone();
+    // Fed by `receive_control` so `audio::send_audio`, spawned below, can degrade its bitrate in
+    // step with the receiver's reported WPM the same way `quality` does for video.
+    let audio_quantization_shift = Arc::new(RwLock::new(0));
+    let cloned_audio_quantization_shift = audio_quantization_shift.clone();
+    let pending_refresh = Arc::new(Mutex::new(RefreshRequest::default()));
+    let cloned_pending_refresh = pending_refresh.clone();
+    // Raw WPM, fed by `receive_control` alongside `quality`, so the skip/fill thresholds below can
+    // be derived from it directly rather than approximating them from `quality`.
+    let wpm = Arc::new(RwLock::new(0.0));
+    let cloned_wpm = wpm.clone();
     std::thread::spawn(|| {
-        receive_control(cloned_quality, receiver_communication_socket);
+        receive_control(cloned_quality, cloned_audio_quantization_shift, cloned_pending_refresh, cloned_wpm, receiver_communication_socket);
+    });
+
+    // Audio gets its own thread with its own SDL context rather than sharing `AudioSubsystem`
+    // across threads: sdl2's subsystem handles aren't `Send`, so the cleanest way to hand one to
+    // a background thread is to never move it there in the first place -- just create it there.
+    // `send_audio` never returns, so this thread does nothing but stream audio for the program's
+    // whole lifetime, same as the camera loop below does for video.
+    let audio_quantization_shift_for_audio_thread = audio_quantization_shift.clone();
+    std::thread::spawn(move || {
+        let sdl_context = sdl2::init().expect("sdl2 init for audio thread");
+        let audio_subsystem = sdl_context.audio().expect("sdl2 audio subsystem");
+        audio::send_audio(&audio_subsystem, audio_quantization_shift_for_audio_thread, audio::AudioHeaderFormat::Compact);
     });
 
-    let mut sender: RtpSlicePayloadSender<u8, PACKET_PAYLOAD_SIZE_THRESHOLD> = rtp::RtpSender::new(sock);
+    let mut last_refresh_handled = Instant::now() - REFRESH_REQUEST_MIN_INTERVAL;
+
+    // Each macroblock's most recently reconstructed value, coded against next frame the same way
+    // a real encoder references its own locally-decoded output rather than the raw source (see
+    // `video::encode_frame_macroblock`).
+    let previous_frame = Arc::new(Mutex::new(vec![
+        Macroblock::default();
+        BLOCK_GRID_WIDTH * BLOCK_GRID_HEIGHT
+    ]));
+    // Each macroblock's most recently used motion vector, so `video::encode_frame_macroblock`'s
+    // search has a predictor to start from next frame (see `video::predicted_motion_vector`).
+    let previous_mv_grid = Arc::new(Mutex::new(vec![
+        MotionVector::default();
+        BLOCK_GRID_WIDTH * BLOCK_GRID_HEIGHT
+    ]));
+
+    let mut sender: rtp::RtpSender<[u8], u8, PACKET_PAYLOAD_SIZE_THRESHOLD, AuthenticatedEncryptedTransport<UdpTransport>> =
+        rtp::RtpSender::with_transport(
+            AuthenticatedEncryptedTransport::new(UdpTransport::new(sock), session_key, session_salt),
+            VIDEO_SSRC,
+            VIDEO_PAYLOAD_TYPE,
+        );
     let sender = Arc::new(Mutex::new(&mut sender));
 
     let mut frame_delay_buffer = FrameCircularBuffer::new();
@@ -186,58 +520,51 @@ pub fn send_video() {
 
         let frame = YUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, frame);
 
-        fn process_block(
-            quality: Arc<RwLock<f64>>,
-            frame: &YUVFrame<'_>,
-            frame_count: u32,
-            x: usize,
-            y: usize,
-            x_end: usize,
-            y_end: usize,
-            sender: Arc<Mutex<&mut RtpSlicePayloadSender<u8, PACKET_PAYLOAD_SIZE_THRESHOLD>>>,
-            packet_buf: Arc<Mutex<Vec<u8>>>,
-        ) {
-            let mut current_macroblock_buf = Vec::with_capacity(PACKET_PAYLOAD_SIZE_THRESHOLD);
-
-            for MacroblockWithPosition { x, y, block } in
-                YUVFrameMacroblockIterator::new_with_bounds(frame, x, y, x_end, y_end)
-            {
-                current_macroblock_buf.clear();
-
-                // get quality
-                // cycle quality between 0.3 and 0.03 based on the current time
-                let quality = quality.read().unwrap().clone();
-
-                let quantized_macroblock = quantize_macroblock(&block, quality);
-
-                current_macroblock_buf.put_u16(x as u16);
-                current_macroblock_buf.put_u16(y as u16);
-                current_macroblock_buf.put_f64(quality);
-                encode_quantized_macroblock(&quantized_macroblock, &mut current_macroblock_buf);
+        // Honor a NACK-style refresh request from the receiver, if one's pending and we aren't
+        // still honoring a recent one: re-encode and resend exactly the requested macroblocks (or
+        // the whole frame) right now, as a standalone packet, instead of waiting for them to come
+        // up again in the regular sweep below.
+        let refresh_request = std::mem::take(&mut *pending_refresh.lock().unwrap());
+        if let Some((x, y, x_end, y_end)) =
+            refresh_region_from_request(&refresh_request, VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize)
+        {
+            if last_refresh_handled.elapsed() >= REFRESH_REQUEST_MIN_INTERVAL {
+                last_refresh_handled = Instant::now();
+                log::info!("Honoring refresh request for ({x}, {y})..({x_end}, {y_end})");
+
+                let frame_timestamp = rtp_epoch().elapsed().as_millis() as u32;
+                let packet_buf = Arc::new(Mutex::new(PacketAccumulator::new(frame_count)));
+                let current_wpm = *wpm.read().unwrap();
+                process_block(
+                    quality.clone(),
+                    wpm::wpm_to_skip_threshold(current_wpm),
+                    wpm::wpm_to_fill_threshold(current_wpm),
+                    previous_frame.clone(),
+                    previous_mv_grid.clone(),
+                    &frame,
+                    frame_count,
+                    frame_timestamp,
+                    x,
+                    y,
+                    x_end,
+                    y_end,
+                    sender.clone(),
+                    packet_buf.clone(),
+                );
 
                 let mut packet_buf = packet_buf.lock().unwrap();
-                if packet_buf.len() + current_macroblock_buf.len() + 2 * size_of::<u16>() >= PACKET_PAYLOAD_SIZE_THRESHOLD {
-                    // send the packet and start a new one
-                    packet_buf.put_u16(u16::MAX);
-                    packet_buf.put_u16(u16::MAX);
-
-                    sender.lock().unwrap().send_bytes(|mem| {
-                        mem[..packet_buf.len()].copy_from_slice(&packet_buf);
-                        packet_buf.len()
+                if packet_buf.buf.len() > size_of::<u32>() {
+                    if let PacketFraming::Legacy = PACKET_FRAMING {
+                        packet_buf.buf.put_u16(u16::MAX);
+                        packet_buf.buf.put_u16(u16::MAX);
+                    }
+                    sender.lock().unwrap().send_bytes(frame_timestamp, false, |mem| {
+                        mem[..packet_buf.buf.len()].copy_from_slice(&packet_buf.buf);
+                        packet_buf.buf.len()
                     });
-                    packet_buf.clear();
-                    packet_buf.put_u32(frame_count);
                 }
-
-                // The macroblock consists of x, y, and the encoded macroblock
-                // log::trace!(
-                //     "Storing macroblock at ({}, {}, {}) at cursor position {}",
-                //     frame_count,
-                //     x,
-                //     y,
-                //     packet_buf.len()
-                // );
-                packet_buf.put_slice(&current_macroblock_buf);
+            } else {
+                log::debug!("Dropping refresh request for ({x}, {y})..({x_end}, {y_end}); still honoring a recent one");
             }
         }
 
@@ -245,12 +572,14 @@ pub fn send_video() {
         assert!(PAR_PACKET_SPAN % MACROBLOCK_X_DIM == 0);
         assert!(PAR_PACKET_SPAN % MACROBLOCK_Y_DIM == 0);
 
-        let mut packet_buf = Vec::with_capacity(PACKET_PAYLOAD_SIZE_THRESHOLD);
-        packet_buf.put_u32(frame_count);
-
-        let packet_buf = Arc::new(Mutex::new(packet_buf));
+        let packet_buf = Arc::new(Mutex::new(PacketAccumulator::new(frame_count)));
 
         let start_seq = sender.lock().unwrap().seq_num();
+        let frame_timestamp = rtp_epoch().elapsed().as_millis() as u32;
+
+        let current_wpm = *wpm.read().unwrap();
+        let skip_threshold = wpm::wpm_to_skip_threshold(current_wpm);
+        let fill_threshold = wpm::wpm_to_fill_threshold(current_wpm);
 
         (0..VIDEO_WIDTH as u32)
             .step_by(PAR_PACKET_SPAN)
@@ -261,8 +590,13 @@ pub fn send_video() {
                     .for_each(|y| {
                         process_block(
                             quality.clone(),
+                            skip_threshold,
+                            fill_threshold,
+                            previous_frame.clone(),
+                            previous_mv_grid.clone(),
                             &frame,
                             frame_count,
+                            frame_timestamp,
                             x as usize,
                             y as usize,
                             x as usize + PAR_PACKET_SPAN,
@@ -272,17 +606,59 @@ pub fn send_video() {
                         );
                     });
             });
-        
-        // send leftover packet, if any
-        let mut packet_buf = packet_buf.lock().unwrap();
-        if packet_buf.len() > 4 {
-            packet_buf.put_u16(u16::MAX);
-            packet_buf.put_u16(u16::MAX);
 
-            sender.lock().unwrap().send_bytes(|mem| {
-                mem[..packet_buf.len()].copy_from_slice(&packet_buf);
-                packet_buf.len()
-            });
+        // Deblock the whole frame's locally-reconstructed reference now that every macroblock in
+        // it has been written (the parallel loop above has joined) — the same
+        // `video::deblock_frame` pass `recv.rs` runs on what it decodes, so the two sides' motion
+        // compensation (see `video::predicted_motion_vector`) never drifts apart from only one
+        // side smoothing its reference frame. `previous_frame` is a `Vec<Macroblock>`, not a pixel
+        // buffer, so it's assembled into one, filtered, then read back apart into macroblocks.
+        {
+            let mut reference_bytes = vec![0u8; VIDEO_WIDTH as usize * VIDEO_HEIGHT as usize * PIXEL_WIDTH];
+            {
+                let grid = previous_frame.lock().unwrap();
+                for (index, block) in grid.iter().enumerate() {
+                    let (gx, gy) = (index % BLOCK_GRID_WIDTH, index / BLOCK_GRID_WIDTH);
+                    block.copy_to_yuv422_frame(
+                        MutableYUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, &mut reference_bytes),
+                        gx * MACROBLOCK_X_DIM,
+                        gy * MACROBLOCK_Y_DIM,
+                    );
+                }
+            }
+
+            deblock_frame(
+                &mut MutableYUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, &mut reference_bytes),
+                *quality.read().unwrap(),
+            );
+
+            let deblocked_frame = YUVFrame::new(VIDEO_WIDTH as usize, VIDEO_HEIGHT as usize, &reference_bytes);
+            let mut grid = previous_frame.lock().unwrap();
+            for MacroblockWithPosition { x, y, block } in YUVFrameMacroblockIterator::new(&deblocked_frame) {
+                grid[(y / MACROBLOCK_Y_DIM) * BLOCK_GRID_WIDTH + x / MACROBLOCK_X_DIM] = block;
+            }
+        }
+
+        // Send the leftover packet, if any. Every macroblock for this frame has been packed by
+        // now (the parallel loop above has joined), so this is always the frame's last packet.
+        let mut packet_buf = packet_buf.lock().unwrap();
+        if packet_buf.buf.len() > 4 {
+            match PACKET_FRAMING {
+                PacketFraming::Legacy => {
+                    packet_buf.buf.put_u16(u16::MAX);
+                    packet_buf.buf.put_u16(u16::MAX);
+                    sender.lock().unwrap().send_bytes(frame_timestamp, true, |mem| {
+                        mem[..packet_buf.buf.len()].copy_from_slice(&packet_buf.buf);
+                        packet_buf.buf.len()
+                    });
+                }
+                PacketFraming::Standard => {
+                    sender.lock().unwrap().send_bytes(frame_timestamp, true, |mem| {
+                        mem[..packet_buf.buf.len()].copy_from_slice(&packet_buf.buf);
+                        packet_buf.buf.len()
+                    });
+                }
+            }
         }
 
         let elapsed = start_time.elapsed();
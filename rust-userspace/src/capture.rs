@@ -0,0 +1,198 @@
+//! Recording and replaying the receiver's consumed video packet stream to/from disk, mirroring
+//! the file-muxing path in tools like Futatabi's `video_stream` or ALVR's video-recording-file: a
+//! bad network run is otherwise gone the moment it's decoded, which makes a flaky macroblock
+//! decoder bug impossible to reproduce on demand.
+//!
+//! A capture only records packets *after* the jitter buffer has already reordered/dropped them
+//! (see [`crate::rtp::RtpCircularBuffer::consume_earliest_packet`]), so replaying one exercises
+//! the macroblock decoder and presentation pacing faithfully, but not the jitter buffer's own
+//! loss/reorder decisions a second time — those already happened once, live, to produce the
+//! packets being recorded.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
+
+use crate::{MACROBLOCK_BYTE_SIZE, MACROBLOCK_X_DIM, MACROBLOCK_Y_DIM, VIDEO_HEIGHT, VIDEO_WIDTH};
+
+/// Distinguishes a real capture file from an arbitrary one passed to `--replay` by mistake.
+const CAPTURE_MAGIC: u32 = 0x4b_4d_43_31; // "KMC1"
+
+/// Fixed-size file header, written once up front, recording the build parameters a capture was
+/// made under. [`CaptureReader::open`] rejects a mismatch outright rather than decoding a
+/// differently-shaped packet stream into garbage macroblocks.
+#[derive(FromBytes, IntoBytes, KnownLayout, Immutable, Debug, Clone, Copy)]
+#[repr(C)]
+struct CaptureHeader {
+    magic: u32,
+    video_width: u32,
+    video_height: u32,
+    macroblock_x_dim: u32,
+    macroblock_y_dim: u32,
+    macroblock_byte_size: u32,
+}
+
+impl CaptureHeader {
+    fn for_current_build() -> Self {
+        CaptureHeader {
+            magic: CAPTURE_MAGIC,
+            video_width: VIDEO_WIDTH,
+            video_height: VIDEO_HEIGHT,
+            macroblock_x_dim: MACROBLOCK_X_DIM as u32,
+            macroblock_y_dim: MACROBLOCK_Y_DIM as u32,
+            macroblock_byte_size: MACROBLOCK_BYTE_SIZE as u32,
+        }
+    }
+
+    fn matches_current_build(&self) -> bool {
+        *self == Self::for_current_build()
+    }
+}
+
+impl PartialEq for CaptureHeader {
+    fn eq(&self, other: &Self) -> bool {
+        self.magic == other.magic
+            && self.video_width == other.video_width
+            && self.video_height == other.video_height
+            && self.macroblock_x_dim == other.macroblock_x_dim
+            && self.macroblock_y_dim == other.macroblock_y_dim
+            && self.macroblock_byte_size == other.macroblock_byte_size
+    }
+}
+
+/// One recorded, already-dejittered video packet: its frame number, how many milliseconds after
+/// the capture started it was consumed, and the raw payload bytes `recv`'s decode loop parses.
+#[derive(Debug, Clone)]
+pub struct CapturedPacket {
+    pub frame_count: u32,
+    pub arrival: Duration,
+    pub data: Vec<u8>,
+}
+
+/// Builds a capture file path that sorts by creation time and won't collide with a previous run:
+/// `<prefix>-<unix epoch millis>.kmcap`.
+pub fn timestamped_capture_path(prefix: &str) -> PathBuf {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    PathBuf::from(format!("{prefix}-{millis}.kmcap"))
+}
+
+/// Writes consumed video packets to a capture file as `recv` plays them, for later reproduction
+/// via [`CaptureReader`].
+pub struct CaptureWriter {
+    file: BufWriter<File>,
+    started_at: Instant,
+}
+
+impl CaptureWriter {
+    /// Creates `path` and writes the header; fails if the file already exists, so a recording
+    /// never silently clobbers a previous one.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = BufWriter::new(
+            File::options().write(true).create_new(true).open(path)?,
+        );
+        file.write_all(CaptureHeader::for_current_build().as_bytes())?;
+        Ok(CaptureWriter { file, started_at: Instant::now() })
+    }
+
+    /// Appends one consumed packet's `frame_count`, its arrival time relative to [`Self::create`]
+    /// (not wall-clock, so recordings are comparable regardless of when they were made), and its
+    /// raw payload bytes: `frame_count: u32`, `arrival_millis: u64`, `len: u32`, `data: [u8; len]`.
+    pub fn write_packet(&mut self, frame_count: u32, data: &[u8]) -> io::Result<()> {
+        let arrival_millis = self.started_at.elapsed().as_millis() as u64;
+        self.file.write_all(&frame_count.to_le_bytes())?;
+        self.file.write_all(&arrival_millis.to_le_bytes())?;
+        self.file.write_all(&(data.len() as u32).to_le_bytes())?;
+        self.file.write_all(data)?;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Reads a capture file written by [`CaptureWriter`] back, one packet at a time, in recording
+/// order.
+pub struct CaptureReader {
+    file: BufReader<File>,
+}
+
+impl CaptureReader {
+    /// Opens `path` and validates its header against this build's `VIDEO_WIDTH`/`VIDEO_HEIGHT`/
+    /// `MACROBLOCK_*` constants, returning an error (rather than panicking deep in the decoder on
+    /// garbage output) if the capture was made under different build parameters.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut file = BufReader::new(File::open(path)?);
+
+        let mut header_bytes = [0u8; size_of::<CaptureHeader>()];
+        file.read_exact(&mut header_bytes)?;
+        let header = CaptureHeader::read_from_bytes(&header_bytes)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "truncated capture header"))?;
+        if header.magic != CAPTURE_MAGIC {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not a keymashed capture file"));
+        }
+        if !header.matches_current_build() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "capture was recorded with different build parameters \
+                     (width {}, height {}, macroblock {}x{}/{}B); refusing to decode it as \
+                     {VIDEO_WIDTH}x{VIDEO_HEIGHT}/{MACROBLOCK_X_DIM}x{MACROBLOCK_Y_DIM}/{MACROBLOCK_BYTE_SIZE}B",
+                    header.video_width,
+                    header.video_height,
+                    header.macroblock_x_dim,
+                    header.macroblock_y_dim,
+                    header.macroblock_byte_size,
+                ),
+            ));
+        }
+
+        Ok(CaptureReader { file })
+    }
+
+    /// Reads the next packet, or `Ok(None)` at a clean end of file.
+    pub fn next_packet(&mut self) -> io::Result<Option<CapturedPacket>> {
+        let mut prefix = [0u8; 4 + 8 + 4];
+        match self.file.read_exact(&mut prefix) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        let frame_count = u32::from_le_bytes(prefix[0..4].try_into().unwrap());
+        let arrival_millis = u64::from_le_bytes(prefix[4..12].try_into().unwrap());
+        let len = u32::from_le_bytes(prefix[12..16].try_into().unwrap()) as usize;
+
+        let mut data = vec![0u8; len];
+        self.file.read_exact(&mut data)?;
+
+        Ok(Some(CapturedPacket { frame_count, arrival: Duration::from_millis(arrival_millis), data }))
+    }
+
+    /// Drives `on_packet` with every remaining packet in the file, sleeping between calls to
+    /// reproduce the original spacing between consumption events — not just a fixed frame rate —
+    /// so the presentation-side pacing (and with it, anything timing-sensitive in the decoder or
+    /// A/V sync logic) is replayed faithfully rather than resampled to a steady rate.
+    pub fn replay_with_original_timing(
+        &mut self,
+        mut on_packet: impl FnMut(u32, &[u8]),
+    ) -> io::Result<()> {
+        let mut previous_arrival = Duration::ZERO;
+        while let Some(packet) = self.next_packet()? {
+            if let Some(gap) = packet.arrival.checked_sub(previous_arrival) {
+                std::thread::sleep(gap);
+            }
+            previous_arrival = packet.arrival;
+            on_packet(packet.frame_count, &packet.data);
+        }
+        Ok(())
+    }
+}
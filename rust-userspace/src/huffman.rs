@@ -0,0 +1,292 @@
+//! Canonical Huffman coding over a byte alphabet, used by [`crate::video::encode_frame`] to
+//! Huffman-code its DC-size/AC-(run,size) symbol streams into an actual bitstream instead of
+//! stopping at a byte-for-symbol representation. Canonical codes only need to ship code *lengths*,
+//! not the bit patterns themselves — [`Table::to_bytes`]/[`Table::from_bytes`] is exactly that,
+//! one length per possible byte value. [`BitWriter`]/[`BitReader`] are the bit-level plumbing
+//! `encode_frame`/`decode_frame` need to interleave those Huffman codes with each coefficient's
+//! literal magnitude bits.
+
+use std::collections::BinaryHeap;
+
+/// Code length (in bits) assigned to each of the 256 possible byte values; `0` means the symbol
+/// never occurred in the data a [`Table`] was built from and so has no code.
+#[derive(Debug, Clone)]
+pub struct Table {
+    code_lengths: [u8; 256],
+    /// Canonical code for each symbol, indexed the same way as `code_lengths`; only meaningful
+    /// where `code_lengths[sym] > 0`.
+    codes: [u32; 256],
+}
+
+#[derive(Eq, PartialEq)]
+struct HeapNode {
+    freq: u64,
+    // Leaves store their symbol; internal nodes are built only to accumulate code lengths, so
+    // they don't need to remember their children once the tree's depths are known.
+    node: NodeKind,
+}
+
+#[derive(Eq, PartialEq)]
+enum NodeKind {
+    Leaf(u8),
+    Internal(Box<HeapNode>, Box<HeapNode>),
+}
+
+impl Ord for HeapNode {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        // Reversed so `BinaryHeap` (a max-heap) pops the *lowest*-frequency node first.
+        other.freq.cmp(&self.freq)
+    }
+}
+
+impl PartialOrd for HeapNode {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+fn assign_lengths(node: &HeapNode, depth: u8, lengths: &mut [u8; 256]) {
+    match &node.node {
+        NodeKind::Leaf(symbol) => {
+            // A single distinct symbol still needs a (1-bit) code to actually emit bits for it.
+            lengths[*symbol as usize] = depth.max(1);
+        }
+        NodeKind::Internal(left, right) => {
+            assign_lengths(left, depth + 1, lengths);
+            assign_lengths(right, depth + 1, lengths);
+        }
+    }
+}
+
+impl Table {
+    /// Builds a canonical Huffman table from `data`'s symbol frequencies.
+    pub fn build(data: &[u8]) -> Self {
+        let mut freq = [0u64; 256];
+        for &b in data {
+            freq[b as usize] += 1;
+        }
+
+        let mut heap: BinaryHeap<HeapNode> = freq
+            .iter()
+            .enumerate()
+            .filter(|&(_, &f)| f > 0)
+            .map(|(symbol, &f)| HeapNode { freq: f, node: NodeKind::Leaf(symbol as u8) })
+            .collect();
+
+        // A single distinct symbol never enters the `while` loop below, so give it an explicit
+        // one-node "tree" up front; `assign_lengths` then gives that lone leaf a 1-bit code.
+        let mut code_lengths = [0u8; 256];
+        if heap.len() == 1 {
+            let only = heap.pop().unwrap();
+            assign_lengths(&only, 0, &mut code_lengths);
+        } else {
+            while heap.len() > 1 {
+                let a = heap.pop().unwrap();
+                let b = heap.pop().unwrap();
+                heap.push(HeapNode {
+                    freq: a.freq + b.freq,
+                    node: NodeKind::Internal(Box::new(a), Box::new(b)),
+                });
+            }
+            if let Some(root) = heap.pop() {
+                assign_lengths(&root, 0, &mut code_lengths);
+            }
+        }
+
+        Self::from_lengths(code_lengths)
+    }
+
+    /// Assigns canonical codes from a set of code lengths: symbols ordered by (length, symbol
+    /// value), each code one more than the previous and shifted left whenever length increases.
+    fn from_lengths(code_lengths: [u8; 256]) -> Self {
+        let mut symbols: Vec<usize> = (0..256).filter(|&s| code_lengths[s] > 0).collect();
+        symbols.sort_by_key(|&s| (code_lengths[s], s));
+
+        let mut codes = [0u32; 256];
+        let mut code = 0u32;
+        let mut prev_len = 0u8;
+        for symbol in symbols {
+            let len = code_lengths[symbol];
+            code <<= len - prev_len;
+            codes[symbol] = code;
+            code += 1;
+            prev_len = len;
+        }
+
+        Self { code_lengths, codes }
+    }
+
+    /// Serializes this table's code lengths, one byte per possible symbol value (`0` = unused) —
+    /// enough on its own for [`Table::from_bytes`] to reconstruct identical canonical codes.
+    pub fn to_bytes(&self) -> [u8; 256] {
+        self.code_lengths
+    }
+
+    pub fn from_bytes(bytes: &[u8; 256]) -> Self {
+        Self::from_lengths(*bytes)
+    }
+
+    /// Encodes `data` into an MSB-first bitstream, padding the final byte with zero bits.
+    pub fn encode(&self, data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut cur_byte = 0u8;
+        let mut bits_in_byte = 0u8;
+
+        for &b in data {
+            let len = self.code_lengths[b as usize];
+            let code = self.codes[b as usize];
+            for bit_index in (0..len).rev() {
+                let bit = (code >> bit_index) & 1;
+                cur_byte = (cur_byte << 1) | bit as u8;
+                bits_in_byte += 1;
+                if bits_in_byte == 8 {
+                    out.push(cur_byte);
+                    cur_byte = 0;
+                    bits_in_byte = 0;
+                }
+            }
+        }
+
+        if bits_in_byte > 0 {
+            cur_byte <<= 8 - bits_in_byte;
+            out.push(cur_byte);
+        }
+
+        out
+    }
+
+    /// This symbol's canonical code and code length, for callers (like
+    /// [`crate::video::encode_frame`]'s DC/AC coder) that need to interleave Huffman codes with
+    /// literal, non-Huffman-coded bits and so can't go through [`Table::encode`]'s whole-buffer
+    /// loop.
+    pub fn code_for(&self, symbol: u8) -> (u32, u8) {
+        (self.codes[symbol as usize], self.code_lengths[symbol as usize])
+    }
+
+    /// Decodes exactly one symbol starting at `reader`'s current position, advancing it past the
+    /// matched code — the single-symbol counterpart to [`Table::code_for`], for readers that
+    /// need to consume literal bits (a coefficient's magnitude) between symbols.
+    pub fn decode_one(&self, reader: &mut BitReader) -> u8 {
+        let mut code = 0u32;
+        let mut len = 0u8;
+        loop {
+            code = (code << 1) | reader.read_bit() as u32;
+            len += 1;
+            for symbol in 0..256 {
+                if self.code_lengths[symbol] == len && self.codes[symbol] == code {
+                    return symbol as u8;
+                }
+            }
+        }
+    }
+
+    /// Decodes exactly `symbol_count` symbols from `bits` (as produced by [`Table::encode`]).
+    pub fn decode(&self, bits: &[u8], symbol_count: usize) -> Vec<u8> {
+        // Canonical codes are prefix-free, so walking bit-by-bit and matching against every
+        // symbol's (length, code) pair is enough to decode without reconstructing a tree.
+        let mut out = Vec::with_capacity(symbol_count);
+        let mut code = 0u32;
+        let mut len = 0u8;
+
+        'bits: for byte in bits {
+            for bit_index in (0..8).rev() {
+                if out.len() == symbol_count {
+                    break 'bits;
+                }
+                let bit = (byte >> bit_index) & 1;
+                code = (code << 1) | bit as u32;
+                len += 1;
+
+                for symbol in 0..256 {
+                    if self.code_lengths[symbol] == len && self.codes[symbol] == code {
+                        out.push(symbol as u8);
+                        code = 0;
+                        len = 0;
+                        break;
+                    }
+                }
+            }
+        }
+
+        out
+    }
+}
+
+/// An MSB-first bit-level writer, for streams that interleave variable-length Huffman codes with
+/// literal fixed-width bit fields (e.g. a coefficient's magnitude bits following its size-category
+/// symbol) — [`Table::encode`] alone only handles a pure symbol stream.
+pub struct BitWriter {
+    bytes: Vec<u8>,
+    cur_byte: u8,
+    bits_in_byte: u8,
+}
+
+impl BitWriter {
+    pub fn new() -> Self {
+        Self { bytes: Vec::new(), cur_byte: 0, bits_in_byte: 0 }
+    }
+
+    /// Appends the low `count` bits of `value`, most-significant bit first.
+    pub fn write_bits(&mut self, value: u32, count: u8) {
+        for bit_index in (0..count).rev() {
+            let bit = (value >> bit_index) & 1;
+            self.cur_byte = (self.cur_byte << 1) | bit as u8;
+            self.bits_in_byte += 1;
+            if self.bits_in_byte == 8 {
+                self.bytes.push(self.cur_byte);
+                self.cur_byte = 0;
+                self.bits_in_byte = 0;
+            }
+        }
+    }
+
+    /// Flushes any partial final byte (padded with zero bits) and returns the packed bytes.
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.bits_in_byte > 0 {
+            self.cur_byte <<= 8 - self.bits_in_byte;
+            self.bytes.push(self.cur_byte);
+        }
+        self.bytes
+    }
+}
+
+impl Default for BitWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The reader counterpart to [`BitWriter`]: walks a byte slice one bit at a time, MSB first.
+/// Reading past the end of `bytes` yields zero bits rather than panicking, since the caller
+/// always knows independently (from a symbol count, or an `Eob`-style in-band terminator) when to
+/// stop — the same tolerance [`BitWriter::finish`]'s zero-padding relies on.
+pub struct BitReader<'a> {
+    bytes: &'a [u8],
+    byte_index: usize,
+    bit_index: u8,
+}
+
+impl<'a> BitReader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, byte_index: 0, bit_index: 0 }
+    }
+
+    pub fn read_bit(&mut self) -> u8 {
+        let byte = self.bytes.get(self.byte_index).copied().unwrap_or(0);
+        let bit = (byte >> (7 - self.bit_index)) & 1;
+        self.bit_index += 1;
+        if self.bit_index == 8 {
+            self.bit_index = 0;
+            self.byte_index += 1;
+        }
+        bit
+    }
+
+    pub fn read_bits(&mut self, count: u8) -> u32 {
+        let mut value = 0u32;
+        for _ in 0..count {
+            value = (value << 1) | self.read_bit() as u32;
+        }
+        value
+    }
+}
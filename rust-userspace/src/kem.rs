@@ -0,0 +1,398 @@
+//! A Kyber-style key encapsulation mechanism (KEM), hand-rolled in the same spirit as
+//! [`crate::chacha20`]/[`crate::poly1305`]: a real (if parameter-reduced) module-lattice KEM, not a
+//! vetted post-quantum crypto crate. Structurally this is "Kyber with k = 1" — a single ring
+//! element instead of a k-dimensional vector of them — which keeps the module to one polynomial
+//! ring worth of arithmetic while still going through every step a real ML-KEM does: a uniform
+//! public polynomial, CBD-sampled secret/error polynomials, a Fujisaki-Okamoto re-encryption check,
+//! and constant-time implicit rejection when that check fails.
+//!
+//! The handshake this enables: the viewer calls [`generate_keypair`] and sends the [`PublicKey`];
+//! the streamer calls [`encapsulate`] and sends back the [`Ciphertext`]; the viewer calls
+//! [`decapsulate`] to recover the same 32-byte secret the streamer got from `encapsulate`. Both
+//! sides then run that secret through [`derive_session_key`] to get the
+//! [`crate::chacha20::KEY_BYTES`] key and [`crate::transport::StreamSalt`]
+//! [`crate::transport::AuthenticatedEncryptedTransport`] needs — replacing
+//! [`crate::TRANSPORT_PRESHARED_KEY`]'s fixed constant with a key that's fresh per session and was
+//! never sent over the wire itself.
+
+use crate::{chacha20, transport::StreamSalt};
+
+/// Ring dimension: polynomials live in `Z_Q[x] / (x^N + 1)`.
+const N: usize = 256;
+/// Modulus coefficients are reduced into `[0, Q)`. The actual Kyber prime.
+const Q: i32 = 3329;
+/// Centered binomial distribution parameter used for every secret/error/noise polynomial — the
+/// same `eta` Kyber-512 uses.
+const ETA: usize = 2;
+/// `round(Q / 2)`, the coefficient [`encode_message`] uses for a `1` bit — as far from `0` (a `0`
+/// bit) as a coefficient mod `Q` can get, so a noisy decode still lands closer to whichever the
+/// original bit was.
+const Q_HALF: i16 = ((Q + 1) / 2) as i16;
+
+type Poly = [i16; N];
+
+fn reduce(x: i32) -> i16 {
+    x.rem_euclid(Q) as i16
+}
+
+fn poly_add(a: &Poly, b: &Poly) -> Poly {
+    std::array::from_fn(|i| reduce(a[i] as i32 + b[i] as i32))
+}
+
+fn poly_sub(a: &Poly, b: &Poly) -> Poly {
+    std::array::from_fn(|i| reduce(a[i] as i32 - b[i] as i32))
+}
+
+/// Negacyclic convolution mod `x^N + 1`: the schoolbook `O(N^2)` way rather than an NTT, since `N`
+/// is small enough here that it doesn't matter and this module isn't trying to be fast, just
+/// correct.
+fn poly_mul(a: &Poly, b: &Poly) -> Poly {
+    let mut acc = [0i32; N];
+    for (i, &ai) in a.iter().enumerate() {
+        if ai == 0 {
+            continue;
+        }
+        for (j, &bj) in b.iter().enumerate() {
+            let product = ai as i32 * bj as i32;
+            let k = i + j;
+            if k < N {
+                acc[k] += product;
+            } else {
+                acc[k - N] -= product;
+            }
+        }
+    }
+    std::array::from_fn(|i| reduce(acc[i]))
+}
+
+/// Fills `buf` with pseudorandom bytes derived from `seed`, standing in for the XOF a real Kyber
+/// implementation would use (SHAKE-128) — [`chacha20::apply_keystream`] is already a perfectly good
+/// stream of pseudorandom bytes keyed off a 32-byte seed, so sampling just reuses it instead of
+/// pulling in a second hash primitive. `domain` and `counter` let different call sites (public
+/// polynomial vs. secret vs. error vs. successive rejection-sampling attempts) draw from
+/// independent streams off the same seed.
+fn expand_into(seed: &[u8; 32], domain: u8, counter: u32, buf: &mut [u8]) {
+    buf.fill(0);
+    let mut nonce = [0u8; chacha20::NONCE_BYTES];
+    nonce[0] = domain;
+    chacha20::apply_keystream(seed, &nonce, counter, buf);
+}
+
+/// Samples the public polynomial `a` uniformly over `Z_Q` via rejection sampling: reads 3 bytes at
+/// a time off `seed`'s expansion, each yielding two 12-bit candidates, keeping a candidate only if
+/// it falls below `Q` — the same "parse" procedure Kyber's spec uses, just driven by
+/// [`expand_into`] instead of SHAKE-128.
+fn sample_uniform(seed: &[u8; 32]) -> Poly {
+    let mut poly = [0i16; N];
+    let mut accepted = 0usize;
+    let mut counter = 0u32;
+    let mut buf = vec![0u8; 3 * N];
+
+    while accepted < N {
+        expand_into(seed, 0, counter, &mut buf);
+        counter += 1;
+
+        for chunk in buf.chunks_exact(3) {
+            if accepted >= N {
+                break;
+            }
+            let d1 = (chunk[0] as u16) | (((chunk[1] as u16) & 0x0f) << 8);
+            let d2 = ((chunk[1] as u16) >> 4) | ((chunk[2] as u16) << 4);
+            if (d1 as i32) < Q {
+                poly[accepted] = d1 as i16;
+                accepted += 1;
+            }
+            if accepted < N && (d2 as i32) < Q {
+                poly[accepted] = d2 as i16;
+                accepted += 1;
+            }
+        }
+    }
+    poly
+}
+
+/// Samples a centered-binomial-distribution polynomial: each coefficient is the difference of two
+/// `ETA`-bit Hamming weights drawn from `seed`'s expansion under `domain`, giving a small
+/// (`[-ETA, ETA]`) noise value per coefficient — used for every secret/error/noise polynomial this
+/// module draws.
+fn sample_cbd(seed: &[u8; 32], domain: u8) -> Poly {
+    let mut buf = [0u8; 2 * ETA * N / 8];
+    expand_into(seed, domain, 0, &mut buf);
+
+    let bit = |index: usize| -> i16 { ((buf[index / 8] >> (index % 8)) & 1) as i16 };
+
+    let mut poly = [0i16; N];
+    let mut bit_index = 0usize;
+    for coeff in poly.iter_mut() {
+        let a: i16 = (0..ETA).map(|_| { let b = bit(bit_index); bit_index += 1; b }).sum();
+        let b: i16 = (0..ETA).map(|_| { let b = bit(bit_index); bit_index += 1; b }).sum();
+        *coeff = reduce((a - b) as i32);
+    }
+    poly
+}
+
+/// Encodes a 32-byte message as a polynomial, one bit per coefficient: `1` becomes [`Q_HALF`], `0`
+/// becomes `0`.
+fn encode_message(message: &[u8; 32]) -> Poly {
+    std::array::from_fn(|i| {
+        let bit = (message[i / 8] >> (i % 8)) & 1;
+        if bit == 1 { Q_HALF } else { 0 }
+    })
+}
+
+/// Inverse of [`encode_message`]: a coefficient decodes to `1` if it's closer to [`Q_HALF`] than to
+/// `0`, tolerating the noise [`poly_mul`]'s accumulated errors add to each coefficient.
+fn decode_message(poly: &Poly) -> [u8; 32] {
+    let mut message = [0u8; 32];
+    for (i, &coeff) in poly.iter().enumerate() {
+        let bit = if (coeff as i32) > Q / 4 && (coeff as i32) < 3 * Q / 4 { 1u8 } else { 0u8 };
+        message[i / 8] |= bit << (i % 8);
+    }
+    message
+}
+
+/// `seed_a` plus the "noisy public polynomial" `t = a*s + e` it's used to derive `a` from — what
+/// the viewer sends the streamer to kick off [`encapsulate`]. Shipping `seed_a` instead of `a`
+/// itself mirrors real Kyber, which does the same to keep the public key small.
+#[derive(Clone)]
+pub struct PublicKey {
+    seed_a: [u8; 32],
+    t: Poly,
+}
+
+/// The viewer's half of the keypair: the secret polynomial `s`, plus an implicit-rejection seed
+/// `z` that never touches the wire and exists purely so [`decapsulate`]'s fallback secret (see
+/// [`conditional_copy`]) is indistinguishable from a real one to anyone without `z`.
+pub struct SecretKey {
+    s: Poly,
+    z: [u8; 32],
+}
+
+/// What [`encapsulate`] sends back to the viewer: the encryption of its ephemeral message under
+/// the viewer's [`PublicKey`].
+#[derive(Clone, PartialEq)]
+pub struct Ciphertext {
+    u: Poly,
+    v: Poly,
+}
+
+/// Wire size of [`PublicKey::to_bytes`]: `seed_a` followed by `t`'s `N` little-endian `i16`
+/// coefficients.
+pub const PUBLIC_KEY_BYTES: usize = 32 + 2 * N;
+
+/// Wire size of [`Ciphertext::to_bytes`]: `u` followed by `v`, each `N` little-endian `i16`
+/// coefficients.
+pub const CIPHERTEXT_BYTES: usize = 4 * N;
+
+impl PublicKey {
+    /// Serializes to the bytes the viewer sends the streamer over the handshake socket to kick
+    /// off [`encapsulate`]: `seed_a` verbatim, then `t`'s coefficients, each little-endian.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(PUBLIC_KEY_BYTES);
+        bytes.extend_from_slice(&self.seed_a);
+        for coeff in self.t.iter() {
+            bytes.extend_from_slice(&coeff.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Inverse of [`Self::to_bytes`]. Panics if `bytes` isn't exactly [`PUBLIC_KEY_BYTES`] long —
+    /// the handshake socket this feeds is a local, trusted loopback pair, not untrusted input.
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        assert_eq!(bytes.len(), PUBLIC_KEY_BYTES, "malformed PublicKey");
+        let mut seed_a = [0u8; 32];
+        seed_a.copy_from_slice(&bytes[..32]);
+        let mut t = [0i16; N];
+        for (coeff, chunk) in t.iter_mut().zip(bytes[32..].chunks_exact(2)) {
+            *coeff = i16::from_le_bytes([chunk[0], chunk[1]]);
+        }
+        PublicKey { seed_a, t }
+    }
+}
+
+impl Ciphertext {
+    /// Serializes to the bytes the streamer sends back to the viewer in response to its
+    /// [`PublicKey`]; just [`ciphertext_bytes`] under a `pub` name for callers outside this module.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        ciphertext_bytes(self)
+    }
+
+    /// Inverse of [`Self::to_bytes`]. Panics if `bytes` isn't exactly [`CIPHERTEXT_BYTES`] long —
+    /// same trusted-local-socket reasoning as [`PublicKey::from_bytes`].
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        assert_eq!(bytes.len(), CIPHERTEXT_BYTES, "malformed Ciphertext");
+        let mut u = [0i16; N];
+        let mut v = [0i16; N];
+        for (coeff, chunk) in u.iter_mut().zip(bytes[..2 * N].chunks_exact(2)) {
+            *coeff = i16::from_le_bytes([chunk[0], chunk[1]]);
+        }
+        for (coeff, chunk) in v.iter_mut().zip(bytes[2 * N..].chunks_exact(2)) {
+            *coeff = i16::from_le_bytes([chunk[0], chunk[1]]);
+        }
+        Ciphertext { u, v }
+    }
+}
+
+/// Generates a fresh keypair. Both `seed_a` (public) and the secret/error/rejection seeds are
+/// drawn from [`random_bytes`].
+pub fn generate_keypair() -> (PublicKey, SecretKey) {
+    let seed_a = random_bytes::<32>();
+    let a = sample_uniform(&seed_a);
+
+    let noise_seed = random_bytes::<32>();
+    let s = sample_cbd(&noise_seed, 0);
+    let e = sample_cbd(&noise_seed, 1);
+    let t = poly_add(&poly_mul(&a, &s), &e);
+
+    let z = random_bytes::<32>();
+
+    (PublicKey { seed_a, t }, SecretKey { s, z })
+}
+
+/// Encrypts `message` under `pk`, deriving the encryption randomness (`r`, `e1`, `e2`) entirely
+/// from `message` itself rather than from fresh entropy — this is the Fujisaki-Okamoto transform's
+/// load-bearing property: re-running this function with the same `message` always reproduces the
+/// same [`Ciphertext`], which is exactly what [`decapsulate`]'s re-encryption check needs.
+fn encrypt_deterministic(pk: &PublicKey, message: &[u8; 32]) -> Ciphertext {
+    let coin_seed = absorb(&[message]);
+    let a = sample_uniform(&pk.seed_a);
+    let r = sample_cbd(&coin_seed, 0);
+    let e1 = sample_cbd(&coin_seed, 1);
+    let e2 = sample_cbd(&coin_seed, 2);
+
+    let u = poly_add(&poly_mul(&a, &r), &e1);
+    let v = poly_add(&poly_add(&poly_mul(&pk.t, &r), &e2), &encode_message(message));
+    Ciphertext { u, v }
+}
+
+/// Serializes a [`Ciphertext`] to bytes purely so it can be folded into [`derive_secret`]'s input —
+/// binding the derived secret to the specific ciphertext transmitted, the same way a real KEM's KDF
+/// does.
+fn ciphertext_bytes(ct: &Ciphertext) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(4 * N);
+    for coeff in ct.u.iter().chain(ct.v.iter()) {
+        bytes.extend_from_slice(&coeff.to_le_bytes());
+    }
+    bytes
+}
+
+/// The streamer's half of the handshake: picks a fresh ephemeral message, encrypts it under the
+/// viewer's `pk`, and returns the resulting [`Ciphertext`] (to send back) alongside the 32-byte
+/// shared secret both sides should end up agreeing on.
+pub fn encapsulate(pk: &PublicKey) -> (Ciphertext, [u8; 32]) {
+    let message = random_bytes::<32>();
+    let ciphertext = encrypt_deterministic(pk, &message);
+    let shared_secret = derive_secret(&[&message, &ciphertext_bytes(&ciphertext)]);
+    (ciphertext, shared_secret)
+}
+
+/// The viewer's half: recovers the streamer's ephemeral message from `ciphertext` using `sk`, then
+/// re-encrypts it to check `ciphertext` was actually produced from that message under `pk` (not
+/// tampered with, and not a ciphertext some other key would decrypt differently). On a match,
+/// returns the same secret [`encapsulate`] derived; otherwise returns a pseudorandom secret tied to
+/// `sk.z` instead of erroring out, so a forged ciphertext can't be distinguished from a genuine one
+/// by whether decapsulation "failed" — the implicit-rejection half of the Fujisaki-Okamoto
+/// transform. The choice between the two is a constant-time [`conditional_copy`], never a branch on
+/// `matches` itself.
+pub fn decapsulate(sk: &SecretKey, pk: &PublicKey, ciphertext: &Ciphertext) -> [u8; 32] {
+    let noisy_message = poly_sub(&ciphertext.v, &poly_mul(&sk.s, &ciphertext.u));
+    let message_prime = decode_message(&noisy_message);
+
+    let recomputed = encrypt_deterministic(pk, &message_prime);
+    let ct_bytes = ciphertext_bytes(ciphertext);
+
+    let real_secret = derive_secret(&[&message_prime, &ct_bytes]);
+    let fallback_secret = derive_secret(&[&sk.z, &ct_bytes]);
+
+    let reject = if ciphertexts_match(ciphertext, &recomputed) { 0u8 } else { 1u8 };
+    let mut secret = real_secret;
+    conditional_copy(&mut secret, &fallback_secret, reject);
+    secret
+}
+
+/// Constant-time equality check over a [`Ciphertext`]'s coefficients, built the same way
+/// [`crate::aead`]'s tag comparison is: every coefficient's XOR gets folded into one accumulator
+/// with `|=` (so which coefficient first differed, if any, can't show up as an early return), and
+/// the final zero/nonzero test goes through `wrapping_neg`'s sign-bit trick rather than `== 0`
+/// directly.
+fn ciphertexts_match(a: &Ciphertext, b: &Ciphertext) -> bool {
+    let mut diff: i32 = 0;
+    for i in 0..N {
+        diff |= (a.u[i] ^ b.u[i]) as u16 as i32;
+        diff |= (a.v[i] ^ b.v[i]) as u16 as i32;
+    }
+    (diff as i64).wrapping_neg() >> 63 == 0
+}
+
+/// Constant-time conditional copy: when `reject` is `1`, every byte of `secret` is overwritten with
+/// the matching byte of `fallback`; when `reject` is `0`, `secret` is left untouched. This is the
+/// exact trick [`decapsulate`] needs to pick between its real and fallback secret without a branch
+/// on secret-dependent data: `wrapping_neg` turns `reject` (`0` or `1`) into an all-zero or
+/// all-one bitmask, and `&` uses that mask to fold `fallback`'s bits into `secret` or not.
+fn conditional_copy(secret: &mut [u8; 32], fallback: &[u8; 32], reject: u8) {
+    let mask = reject.wrapping_neg();
+    for i in 0..secret.len() {
+        secret[i] ^= mask & (fallback[i] ^ secret[i]);
+    }
+}
+
+/// Folds arbitrary-length `parts` into a pseudorandom 32-byte digest by running each 32-byte chunk
+/// of their concatenation through [`chacha20::apply_keystream`] as a compression step, XORing in
+/// the running state as we go — a toy Davies-Meyer-style sponge over ChaCha20's block function,
+/// standing in for a real hash (SHA3/BLAKE, say) the same way [`expand_into`] stands in for a real
+/// XOF. Good enough to mix a handshake transcript into a seed; not a vetted hash function.
+fn absorb(parts: &[&[u8]]) -> [u8; 32] {
+    let mut input = Vec::new();
+    for part in parts {
+        input.extend_from_slice(part);
+    }
+
+    let mut state = [0u8; 32];
+    let nonce = [0u8; chacha20::NONCE_BYTES];
+    for (counter, chunk) in input.chunks(32).enumerate() {
+        let mut key = [0u8; 32];
+        key[..chunk.len()].copy_from_slice(chunk);
+        for (k, s) in key.iter_mut().zip(state.iter()) {
+            *k ^= s;
+        }
+        chacha20::apply_keystream(&key, &nonce, counter as u32, &mut state);
+    }
+    state
+}
+
+/// [`absorb`], then expanded back out to `out_len` bytes via [`expand_into`] — this module's
+/// stand-in KDF, used to turn a handshake transcript (message/ciphertext/fallback-seed bytes) into
+/// fixed-size key material.
+fn derive_secret(parts: &[&[u8]]) -> [u8; 32] {
+    let seed = absorb(parts);
+    let mut out = [0u8; 32];
+    expand_into(&seed, 0xff, 0, &mut out);
+    out
+}
+
+/// Turns a KEM shared secret into the ChaCha20 key and [`StreamSalt`]
+/// [`crate::transport::AuthenticatedEncryptedTransport`] needs, in place of
+/// [`crate::TRANSPORT_PRESHARED_KEY`]'s fixed constant — both sides of the handshake call this on
+/// the same `shared_secret` and end up with the same key/salt pair without either value ever having
+/// been sent.
+pub fn derive_session_key(shared_secret: &[u8; 32]) -> ([u8; chacha20::KEY_BYTES], StreamSalt) {
+    let mut material = [0u8; chacha20::KEY_BYTES + 8];
+    expand_into(shared_secret, 0xfe, 0, &mut material);
+    let key = material[..chacha20::KEY_BYTES].try_into().unwrap();
+    let salt = material[chacha20::KEY_BYTES..].try_into().unwrap();
+    (key, salt)
+}
+
+/// OS-backed randomness for a keypair/handshake's secret material (`s`, `e`, the ephemeral
+/// Fujisaki-Okamoto message, and the implicit-rejection fallback seed `z`). This module used to
+/// get these bytes from hashing a counter under a freshly constructed
+/// [`std::collections::hash_map::RandomState`] — that type draws a seed from OS randomness once
+/// per thread, but it's documented as a DOS-resistance mechanism, not a CSPRNG: after the first
+/// call it's just counting up from a cached seed, not resampling entropy, so the later outputs are
+/// far more predictable than the security implicit rejection is supposed to buy. `getrandom` reads
+/// straight from the OS's CSPRNG (`getrandom(2)`/`/dev/urandom`-equivalent) every call instead.
+fn random_bytes<const LEN: usize>() -> [u8; LEN] {
+    let mut out = [0u8; LEN];
+    getrandom::getrandom(&mut out).expect("OS RNG unavailable");
+    out
+}
@@ -1,15 +1,32 @@
 #![feature(generic_const_exprs)]
 
-use std::{io::Write, net::UdpSocket, time::Duration};
+use std::{
+    io::Write,
+    net::UdpSocket,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
 
 use simplelog::WriteLogger;
 use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout};
 
+pub mod aead;
 pub mod audio;
+pub mod audio_codec;
 pub mod bpf;
+pub mod capture;
+pub mod chacha20;
+pub mod huffman;
+pub mod kem;
+pub mod poly1305;
+pub mod ratecontrol;
 pub mod rtp;
+pub mod rtp_rfc3550;
+pub mod stats;
+pub mod transport;
 pub mod video;
 pub mod wpm;
+pub mod y4m;
 
 pub const VIDEO_WIDTH: u32 = 640;
 pub const VIDEO_HEIGHT: u32 = 480;
@@ -25,6 +42,18 @@ pub const BUFFER_LOGS: bool = false;
 /// Maximum size of packet payloads. (Tries to correspond to Ethernet MTU)
 pub const PACKET_PAYLOAD_SIZE_THRESHOLD: usize = 1400;
 
+/// Fixed SSRC identifiers (see [`rtp::PacketHeader::ssrc`]) for this process's two media streams,
+/// so a receiver sharing one socket can tell them apart the way real RTP's SSRC (RFC 3550 5.1)
+/// does. A production implementation would pick these randomly per session; fixed values are
+/// enough here since there's only ever one audio sender and one video sender.
+pub const AUDIO_SSRC: u32 = 0x41_55_44_31; // "AUD1"
+pub const VIDEO_SSRC: u32 = 0x56_49_44_31; // "VID1"
+
+/// Payload-type identifiers (see [`rtp::PacketHeader::payload_type`]) for this process's two
+/// media streams, picked from RTP's dynamic range (RFC 3551 6).
+pub const AUDIO_PAYLOAD_TYPE: u8 = 97;
+pub const VIDEO_PAYLOAD_TYPE: u8 = 96;
+
 /// IP address of the machine running the `recv` binary.
 pub const RECV_IP: &str = "127.0.0.1";
 /// IP address of the machine running the `send` binary.
@@ -43,14 +72,76 @@ pub const RECV_CONTROL_PORT: u16 = 51902;
 /// Port on send for control messages.
 pub const SEND_CONTROL_PORT: u16 = 44601;
 
+/// Pre-shared key for [`transport::EncryptedTransport`], fixed here the same way [`AUDIO_SSRC`]/
+/// [`VIDEO_SSRC`] are fixed rather than negotiated. The `send` and `recv` binaries do not use it:
+/// they derive a per-session key through [`kem`]'s handshake instead.
+pub const TRANSPORT_PRESHARED_KEY: [u8; chacha20::KEY_BYTES] = *b"keymashed-demo-preshared-key!!!!";
+
 pub const PIXEL_WIDTH: usize = 2;
 pub const MACROBLOCK_X_DIM: usize = 16;
 pub const MACROBLOCK_Y_DIM: usize = 16;
 pub const MACROBLOCK_BYTE_SIZE: usize = MACROBLOCK_X_DIM * MACROBLOCK_Y_DIM * PIXEL_WIDTH;
 
+/// A receiver's request, piggybacked on the control channel, that the sender force an intra
+/// refresh of (part of) the next frame — mirroring the `request-keyframe`/PLI idiom RTP
+/// depayloaders use to recover from loss, adapted to this codec's per-macroblock coding.
+#[derive(FromBytes, IntoBytes, KnownLayout, Immutable, Debug, Clone, Copy, Default)]
+#[repr(C)]
+pub struct RefreshRequest {
+    /// Whether a refresh is being requested at all. Not a `bool` so the struct stays `FromBytes`
+    /// over arbitrary wire bytes; `0` means "no request", anything else means "yes".
+    pub requested: u8,
+    _padding: [u8; 3],
+    /// Macroblock-grid bounds (in macroblock units, not pixels) of the span to refresh. Ignored
+    /// when `requested` is 0. An empty range (`x_end <= x_start` or `y_end <= y_start`) means
+    /// "the whole frame" rather than nothing, so a receiver that can't localize the damage can
+    /// still ask for a full keyframe.
+    pub x_start: u16,
+    pub y_start: u16,
+    pub x_end: u16,
+    pub y_end: u16,
+}
+
+/// A receiver's report, piggybacked on the control channel, of the largest payload it can
+/// currently accept without the path fragmenting it — the playout-side half of the path-MTU
+/// negotiation in [`rtp`]; a sender can combine this with its own [`rtp::probe_path_mtu`] probe
+/// and call [`rtp::RtpSender::set_payload_size_limit`] with whichever is smaller. The `send`
+/// binary does not read this field, and `recv` always reports `0`.
+#[derive(FromBytes, IntoBytes, KnownLayout, Immutable, Debug, Clone, Copy)]
+#[repr(C)]
+pub struct MtuReport {
+    /// Usable payload size in bytes, as observed by the receiver. `0` means "no opinion yet",
+    /// so a freshly-started receiver doesn't clamp the sender down before it has a real estimate.
+    pub usable_payload_bytes: u32,
+}
+
 #[derive(FromBytes, IntoBytes, KnownLayout, Immutable, Debug, Clone, Copy)]
 pub struct ControlMessage {
     pub quality: f64,
+    /// Desired audio codec `quantization_shift` (see `audio_codec::encode`), computed the same way
+    /// as `quality` (see `wpm::wpm_to_audio_quantization_shift`) so audio degrades gracefully under
+    /// keymashing alongside video.
+    pub audio_quantization_shift: u32,
+    pub refresh_request: RefreshRequest,
+    pub mtu_report: MtuReport,
+    /// The FEC group size the receiver wants the sender using (see
+    /// [`rtp::RtpSender::set_fec_group_size`]), so both ends agree on how to interpret
+    /// [`rtp::PacketHeader::fec_group_size`] — `0` means "no FEC". Purely a request: the `send`
+    /// binary does not read it, and `recv` always sends `0`.
+    pub fec_group_size: u8,
+    /// The receiver's raw typing rate, piggybacked alongside the `quality`/`audio_quantization_shift`
+    /// it's already derived from, so `video::encode_frame_macroblock`'s skip/fill thresholds (see
+    /// `wpm::wpm_to_skip_threshold`/`wpm::wpm_to_fill_threshold`) can be computed sender-side —
+    /// that decision lives in `send.rs`'s per-frame loop, which otherwise never sees WPM directly.
+    pub wpm: f64,
+}
+
+static RTP_EPOCH: OnceLock<Instant> = OnceLock::new();
+
+/// A monotonic origin shared by every capture thread in this process, so that presentation
+/// timestamps sampled independently by `send_audio` and `send_video` are directly comparable.
+pub fn rtp_epoch() -> Instant {
+    *RTP_EPOCH.get_or_init(Instant::now)
 }
 
 pub fn init_logger(_is_send: bool) {
@@ -140,7 +140,7 @@ fn main() -> std::io::Result<()> {
                 }
 
                 let packet = locked_video_reciever.consume_earliest_packet();
-                if let Some(packet) = packet.get_data() {
+                if let Some(packet) = packet.as_ref().and_then(|p| p.get_data()) {
                     // copy the packet data into the buffer
                     let mut cursor = &packet.data.data[..];
 
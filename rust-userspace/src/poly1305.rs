@@ -0,0 +1,168 @@
+//! A minimal Poly1305 one-time authenticator (RFC 8439 section 2.5), hand-rolled in the same
+//! spirit as [`crate::chacha20`]: enough to produce/verify a MAC over a wire payload for
+//! [`crate::aead`], not a vetted general-purpose crypto crate. Ported from the well-known
+//! "poly1305-donna" 32-bit reference implementation, which represents the running accumulator as
+//! five 26-bit limbs so every intermediate product fits comfortably in a `u64` and the modular
+//! reduction mod `2^130 - 5` only ever needs cheap shifts, masks, and a multiply-by-5.
+
+const LIMB_MASK: u64 = 0x3ff_ffff;
+
+/// The one-time key's first half, clamped per RFC 8439 (certain bits of `r` are forced to zero so
+/// the field multiplication below can't overflow its 26-bit limbs), split into the five 26-bit
+/// limbs the rest of this module works in.
+fn clamped_r_limbs(key: &[u8; 32]) -> [u64; 5] {
+    let r0 = u32::from_le_bytes(key[0..4].try_into().unwrap());
+    let r1 = u32::from_le_bytes(key[3..7].try_into().unwrap());
+    let r2 = u32::from_le_bytes(key[6..10].try_into().unwrap());
+    let r3 = u32::from_le_bytes(key[9..13].try_into().unwrap());
+    let r4 = u32::from_le_bytes(key[12..16].try_into().unwrap());
+
+    [
+        (r0 & 0x3ff_ffff) as u64,
+        ((r1 >> 2) & 0x3ff_ff03) as u64,
+        ((r2 >> 4) & 0x3ff_c0ff) as u64,
+        ((r3 >> 6) & 0x3f0_3fff) as u64,
+        ((r4 >> 8) & 0x00f_ffff) as u64,
+    ]
+}
+
+/// Folds one 16-byte message block (already zero-padded, with `hi_bit` set for a genuine full
+/// block and clear for the final short block's explicit `0x01` pad byte) into the accumulator
+/// `h`, then reduces the result back mod `2^130 - 5`.
+fn absorb_block(h: &mut [u64; 5], r: &[u64; 5], block: &[u8; 16], hi_bit: u64) {
+    let m0 = u32::from_le_bytes(block[0..4].try_into().unwrap());
+    let m1 = u32::from_le_bytes(block[3..7].try_into().unwrap());
+    let m2 = u32::from_le_bytes(block[6..10].try_into().unwrap());
+    let m3 = u32::from_le_bytes(block[9..13].try_into().unwrap());
+    let m4 = u32::from_le_bytes(block[12..16].try_into().unwrap());
+
+    h[0] += (m0 & 0x3ff_ffff) as u64;
+    h[1] += ((m1 >> 2) & 0x3ff_ffff) as u64;
+    h[2] += ((m2 >> 4) & 0x3ff_ffff) as u64;
+    h[3] += ((m3 >> 6) & 0x3ff_ffff) as u64;
+    h[4] += ((m4 >> 8) as u64) | hi_bit;
+
+    // h *= r (mod 2^130 - 5), using `s_i = 5 * r_i` so the limbs that would otherwise land above
+    // bit 130 fold back in scaled by 5, since `2^130 === 5 (mod 2^130 - 5)`.
+    let s1 = r[1] * 5;
+    let s2 = r[2] * 5;
+    let s3 = r[3] * 5;
+    let s4 = r[4] * 5;
+
+    let d0 = h[0] * r[0] + h[1] * s4 + h[2] * s3 + h[3] * s2 + h[4] * s1;
+    let d1 = h[0] * r[1] + h[1] * r[0] + h[2] * s4 + h[3] * s3 + h[4] * s2;
+    let d2 = h[0] * r[2] + h[1] * r[1] + h[2] * r[0] + h[3] * s4 + h[4] * s3;
+    let d3 = h[0] * r[3] + h[1] * r[2] + h[2] * r[1] + h[3] * r[0] + h[4] * s4;
+    let d4 = h[0] * r[4] + h[1] * r[3] + h[2] * r[2] + h[3] * r[1] + h[4] * r[0];
+
+    // Partial carry propagation, wrapping the overflow out of the top limb back into the bottom
+    // one scaled by 5 (same `2^130 === 5` identity as above).
+    let mut carry;
+    carry = d0 >> 26;
+    h[0] = d0 & LIMB_MASK;
+    let d1 = d1 + carry;
+    carry = d1 >> 26;
+    h[1] = d1 & LIMB_MASK;
+    let d2 = d2 + carry;
+    carry = d2 >> 26;
+    h[2] = d2 & LIMB_MASK;
+    let d3 = d3 + carry;
+    carry = d3 >> 26;
+    h[3] = d3 & LIMB_MASK;
+    let d4 = d4 + carry;
+    carry = d4 >> 26;
+    h[4] = d4 & LIMB_MASK;
+    h[0] += carry * 5;
+    carry = h[0] >> 26;
+    h[0] &= LIMB_MASK;
+    h[1] += carry;
+}
+
+/// Computes the 16-byte Poly1305 tag for `message` under one-time `key` (the first 16 bytes are
+/// the clamped field element `r`, the last 16 are the additive mask `s`). `key` must never be
+/// reused across two different messages — [`crate::aead`] derives a fresh one per packet from the
+/// ChaCha20 keystream precisely so this one-time requirement holds.
+pub fn compute_tag(key: &[u8; 32], message: &[u8]) -> [u8; 16] {
+    let r = clamped_r_limbs(key);
+    let mut h = [0u64; 5];
+
+    let mut chunks = message.chunks_exact(16);
+    for block in &mut chunks {
+        absorb_block(&mut h, &r, block.try_into().unwrap(), 1 << 24);
+    }
+
+    let remainder = chunks.remainder();
+    if !remainder.is_empty() {
+        let mut padded = [0u8; 16];
+        padded[..remainder.len()].copy_from_slice(remainder);
+        padded[remainder.len()] = 1;
+        absorb_block(&mut h, &r, &padded, 0);
+    }
+
+    // Fully carry h (the partial reduction inside `absorb_block` can leave a small carry sitting
+    // in a limb above its 26 bits).
+    let mut carry = h[1] >> 26;
+    h[1] &= LIMB_MASK;
+    h[2] += carry;
+    carry = h[2] >> 26;
+    h[2] &= LIMB_MASK;
+    h[3] += carry;
+    carry = h[3] >> 26;
+    h[3] &= LIMB_MASK;
+    h[4] += carry;
+    carry = h[4] >> 26;
+    h[4] &= LIMB_MASK;
+    h[0] += carry * 5;
+    carry = h[0] >> 26;
+    h[0] &= LIMB_MASK;
+    h[1] += carry;
+
+    // Compute h - p (p = 2^130 - 5) and select it instead of h whenever h >= p, so the final
+    // value is always fully reduced into [0, p) before truncating to 128 bits.
+    let mut g = [0u64; 5];
+    g[0] = h[0] + 5;
+    carry = g[0] >> 26;
+    g[0] &= LIMB_MASK;
+    g[1] = h[1] + carry;
+    carry = g[1] >> 26;
+    g[1] &= LIMB_MASK;
+    g[2] = h[2] + carry;
+    carry = g[2] >> 26;
+    g[2] &= LIMB_MASK;
+    g[3] = h[3] + carry;
+    carry = g[3] >> 26;
+    g[3] &= LIMB_MASK;
+    g[4] = h[4] + carry;
+    // `g[4]` only ever needed 27 bits above to hold `h[4] - 2^26`; if that subtraction would have
+    // gone negative, h was already < p and `h` (not `g`) is the fully reduced value.
+    let use_g = if g[4] >= (1 << 26) {
+        g[4] = g[4].wrapping_sub(1 << 26);
+        true
+    } else {
+        false
+    };
+    let h = if use_g { g } else { h };
+
+    // Pack the five 26-bit limbs into 128 bits, then add the `s` half of the key mod 2^128.
+    let h0 = (h[0] | (h[1] << 26)) & 0xffff_ffff;
+    let h1 = ((h[1] >> 6) | (h[2] << 20)) & 0xffff_ffff;
+    let h2 = ((h[2] >> 12) | (h[3] << 14)) & 0xffff_ffff;
+    let h3 = ((h[3] >> 18) | (h[4] << 8)) & 0xffff_ffff;
+
+    let pad0 = u32::from_le_bytes(key[16..20].try_into().unwrap()) as u64;
+    let pad1 = u32::from_le_bytes(key[20..24].try_into().unwrap()) as u64;
+    let pad2 = u32::from_le_bytes(key[24..28].try_into().unwrap()) as u64;
+    let pad3 = u32::from_le_bytes(key[28..32].try_into().unwrap()) as u64;
+
+    let f0 = h0 + pad0;
+    let f1 = h1 + pad1 + (f0 >> 32);
+    let f2 = h2 + pad2 + (f1 >> 32);
+    let f3 = h3 + pad3 + (f2 >> 32);
+
+    let mut tag = [0u8; 16];
+    tag[0..4].copy_from_slice(&(f0 as u32).to_le_bytes());
+    tag[4..8].copy_from_slice(&(f1 as u32).to_le_bytes());
+    tag[8..12].copy_from_slice(&(f2 as u32).to_le_bytes());
+    tag[12..16].copy_from_slice(&(f3 as u32).to_le_bytes());
+    tag
+}
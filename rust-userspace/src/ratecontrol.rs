@@ -0,0 +1,153 @@
+//! Closed-loop bitrate control, as an alternative to mapping WPM straight to a `quality` knob
+//! (see [`crate::wpm::wpm_to_jpeg_quality`]): [`RateController`] watches each frame's actual
+//! entropy-coded size (see [`crate::video::encode_frame`]) and nudges `quality` toward whatever
+//! keeps the stream converging on a target bits-per-frame budget, the way a real muxer/encoder
+//! manages packet sizing instead of reacting to a single frame's size in one jump.
+
+use std::collections::VecDeque;
+
+/// How many recent frame sizes [`RateController`] averages before reacting, so one unusually
+/// cheap or expensive frame (e.g. a skip-heavy static scene) doesn't whip `quality` back and
+/// forth frame to frame.
+const MOVING_AVERAGE_WINDOW: usize = 5;
+
+/// How aggressively [`RateController::update`] corrects `quality` per frame, as a fraction of
+/// `bounds`'s span. Only used as a fallback while [`RateController::bits_per_quality`] hasn't
+/// seen a usable quality step to estimate a slope from yet (startup, or a run of frames at
+/// exactly the same quality).
+const PROPORTIONAL_STEP: f64 = 0.05;
+
+/// Below this magnitude, [`RateController::bits_per_quality`]'s slope estimate is treated as too
+/// noisy to divide by (a near-flat bits/quality curve would otherwise predict an enormous step),
+/// and `update` falls back to [`PROPORTIONAL_STEP`] instead.
+const MIN_USABLE_SLOPE: f64 = 1.0;
+
+/// Smoothing weight for folding each frame's observed (quality step, bits delta) pair into
+/// [`RateController::bits_per_quality`]'s running slope estimate — closer to 1.0 reacts faster to
+/// scene changes, closer to 0.0 stays steadier against noise.
+const SLOPE_EMA_WEIGHT: f64 = 0.3;
+
+/// Fraction of [`RateController::bucket`]'s accumulated over/undershoot left to carry into the
+/// next frame's prediction after this frame's correction — a leak rather than a hard reset, so a
+/// burst of overshoot still biases predictions for a few frames after the frame that caused it
+/// has aged out of the moving average.
+const BUCKET_LEAK: f64 = 0.8;
+
+/// How strongly [`RateController::bucket`]'s accumulated over/undershoot (in bits) biases the
+/// next predicted step, alongside the current frame's own error against `target_bits`.
+const BUCKET_BIAS_WEIGHT: f64 = 0.25;
+
+/// Largest `quality` adjustment [`RateController::update`] will make in a single frame,
+/// regardless of what the slope prediction or bucket bias call for — mirrors the RV40 encoder's
+/// rate controller clamping its own per-frame quantizer step to avoid a visible pulse every time
+/// a scene cut or sudden run of motion swings the predicted step wide.
+const MAX_QUALITY_STEP: f64 = 0.04;
+
+/// Valid range for the `quality` a [`RateController`] produces, matching the scale
+/// [`crate::wpm::wpm_to_jpeg_quality`] already operates on.
+pub const QUALITY_BOUNDS: (f64, f64) = (0.03, 1.0);
+
+/// Feedback controller targeting `target_bits` bits per frame, modeled on the RV40 encoder's rate
+/// control loop: a running estimate of bits-per-quality-unit predicts the step that should hit
+/// the target directly (falling back to plain proportional control until that estimate is
+/// trustworthy), the step is clamped so quality can't pulse by more than a few units in one
+/// frame, and a leaky bucket of accumulated over/undershoot biases the prediction so a burst of
+/// oversized frames keeps being corrected for after it's aged out of the moving average. Construct
+/// once per stream and call [`RateController::update`] with each frame's actual encoded size in
+/// bits; it returns the `quality` to use for the *next* frame, clamped to `bounds`.
+pub struct RateController {
+    target_bits: f64,
+    bounds: (f64, f64),
+    quality: f64,
+    recent_bits: VecDeque<f64>,
+    /// This frame's `quality`/actual bits, kept around so the *next* `update` can turn the
+    /// (quality step, bits delta) pair between this frame and that one into a slope observation.
+    last_quality: Option<f64>,
+    last_bits: f64,
+    /// Running estimate of bits-per-quality-unit, updated from consecutive frames' observed
+    /// (quality step, bits delta) pairs. Positive, since this scale's higher `quality` numbers
+    /// mean finer quantization and therefore more bits.
+    bits_per_quality: f64,
+    /// Accumulated (actual minus target) bits, leaking toward zero by [`BUCKET_LEAK`] each frame
+    /// instead of resetting outright. See the struct-level doc for why.
+    bucket: f64,
+}
+
+impl RateController {
+    /// `bounds` is `(min, max)`; `quality` starts at their midpoint until the first `update`.
+    pub fn new(target_bits: f64, bounds: (f64, f64)) -> Self {
+        Self {
+            target_bits,
+            bounds,
+            quality: (bounds.0 + bounds.1) / 2.0,
+            recent_bits: VecDeque::new(),
+            last_quality: None,
+            last_bits: 0.0,
+            bits_per_quality: 0.0,
+            bucket: 0.0,
+        }
+    }
+
+    /// Re-targets the budget frame to frame (e.g. scaled by WPM via [`wpm_to_target_bits`])
+    /// without losing the controller's accumulated moving average, slope estimate, or bucket.
+    pub fn set_target_bits(&mut self, target_bits: f64) {
+        self.target_bits = target_bits;
+    }
+
+    /// The `quality` to use for the frame about to be encoded — whatever the last [`update`]
+    /// call (or the `bounds` midpoint, before the first one) converged on.
+    ///
+    /// [`update`]: RateController::update
+    pub fn quality(&self) -> f64 {
+        self.quality
+    }
+
+    /// Folds in the most recently observed frame's actual size (in bits), and returns the
+    /// `quality` to use for the next frame.
+    pub fn update(&mut self, actual_bits: f64) -> f64 {
+        self.recent_bits.push_back(actual_bits);
+        if self.recent_bits.len() > MOVING_AVERAGE_WINDOW {
+            self.recent_bits.pop_front();
+        }
+        let averaged_bits = self.recent_bits.iter().sum::<f64>() / self.recent_bits.len() as f64;
+
+        if let Some(last_quality) = self.last_quality {
+            let quality_step = self.quality - last_quality;
+            if quality_step.abs() > f64::EPSILON {
+                let observed_slope = (actual_bits - self.last_bits) / quality_step;
+                self.bits_per_quality =
+                    self.bits_per_quality * (1.0 - SLOPE_EMA_WEIGHT) + observed_slope * SLOPE_EMA_WEIGHT;
+            }
+        }
+        self.last_quality = Some(self.quality);
+        self.last_bits = actual_bits;
+
+        self.bucket = self.bucket * BUCKET_LEAK + (averaged_bits - self.target_bits);
+        let error = self.target_bits - averaged_bits - BUCKET_BIAS_WEIGHT * self.bucket;
+
+        let predicted_step = if self.bits_per_quality.abs() >= MIN_USABLE_SLOPE {
+            error / self.bits_per_quality
+        } else {
+            let span = self.bounds.1 - self.bounds.0;
+            PROPORTIONAL_STEP * span * error / self.target_bits
+        };
+
+        self.quality += predicted_step.clamp(-MAX_QUALITY_STEP, MAX_QUALITY_STEP);
+        self.quality = self.quality.clamp(self.bounds.0, self.bounds.1);
+        self.quality
+    }
+}
+
+/// Fewest bits-per-frame budgeted at 0 WPM.
+const WORST_TARGET_BITS: f64 = 40_000.0;
+/// Most bits-per-frame budgeted at saturation WPM — faster typing "buys" a bigger budget, the
+/// same direction as [`crate::wpm::wpm_to_vq_codebook_size`]'s codebook size.
+const BEST_TARGET_BITS: f64 = 400_000.0;
+
+/// Scales [`RateController`]'s target bits-per-frame budget with typing speed, on the same
+/// saturation curve as the rest of [`crate::wpm`]'s knobs.
+pub fn wpm_to_target_bits(wpm: f64) -> f64 {
+    let clipped_wpm = wpm.min(crate::wpm::WPM_SATURATION);
+    let wpm_ratio = clipped_wpm / crate::wpm::WPM_SATURATION;
+    WORST_TARGET_BITS + (BEST_TARGET_BITS - WORST_TARGET_BITS) * wpm_ratio
+}
@@ -12,6 +12,54 @@
 //! - For `dyn Trait` objects, use the type that the dyn was derived from.
 //!
 //! Read more about alignment in Rust [here](https://doc.rust-lang.org/reference/type-layout.html).
+//!
+//! [`RtpReceiver::receiver_report`] exposes loss/jitter bookkeeping (à la RTCP receiver reports)
+//! so a caller can feed it back to the sender and have [`ReceiverReport::scale_budget`] throttle
+//! whatever send-side budget (packet rate, JPEG quality, ...) is appropriate for that stream.
+//!
+//! [`RtpReceiver::clock_drift_offset_millis`] exposes a smoothed sender/receiver clock offset, so
+//! a playback loop can convert a packet's timestamp into a stable local target time instead of
+//! guessing freshness from how full the circular buffer looks.
+//!
+//! [`RtpReceiver::target_buffer_span`] exposes an adaptive jitter-buffer depth, computed from the
+//! same jitter estimate, so a playback loop's `early_latest_span() < target` wait can grow under
+//! turbulence and shrink toward minimal latency on a calm link instead of using a fixed constant.
+//!
+//! `SLOT_SIZE` still bounds how large the preallocated per-packet scratch buffer is (it has to be
+//! a const generic, since it sizes a fixed-size array), but it is no longer the packet size a
+//! sender actually uses on the wire. [`RtpSender::set_payload_size_limit`] lifts that decision
+//! into runtime state, so a path that can't carry `SLOT_SIZE`-sized packets without fragmenting
+//! can be negotiated down to whatever it can carry instead. [`probe_path_mtu`] gives a sender a
+//! starting guess at startup; a caller can tighten it further from whatever the receiver reports
+//! back over its own side channel (e.g. [`crate::MtuReport`]).
+//!
+//! A logical payload bigger than one packet can still hold ([`RtpSender::send_fragmented`] splits
+//! it across consecutive sequence numbers), and [`RtpCircularBuffer::reassemble_frame`] reverses
+//! that split once every fragment has landed. There's no separate frame-id/fragment-index/
+//! fragment-count header fields for this: a fragmented frame's "id" is just the sequence number
+//! its [`PacketHeader::fragment_start`] packet landed on, its "index" is a fragment's offset from
+//! that sequence number, and its "count" is implicit in where the run hits a
+//! [`PacketHeader::fragment_end`] packet — reusing the reorder window's own sequence-number
+//! contiguity instead of duplicating it in a second header field. A fragment that never arrives
+//! (or gets evicted from the window before the rest of its frame does) leaves the run permanently
+//! incomplete, so [`RtpCircularBuffer::reassemble_frame`] drops it the same way it would a gap.
+//!
+//! `BUFFER_LENGTH` likewise only sizes the reorder window at startup; [`RtpCircularBuffer::limits`]
+//! reports how full it is (à la TCP buffer limits), and [`RtpCircularBuffer::set_target_capacity`]
+//! grows or shrinks it at runtime, so a caller can widen the window under heavy loss and reclaim
+//! the memory once the link settles down.
+//!
+//! Packet metadata and packet bytes are stored separately: the reorder window (`meta`) is a ring
+//! of small [`PacketMeta`] entries, while payload bytes live packed back-to-back in a single
+//! shared [`AlignedArena`] sized once to `SLOT_SIZE * BUFFER_LENGTH` bytes. This means a deeper
+//! reorder window (via [`RtpCircularBuffer::set_target_capacity`]) no longer also multiplies how
+//! much payload memory is reserved, since `meta` resizes independently of `arena`.
+//!
+//! [`RtpSender`]/[`RtpReceiver`] are generic over how bytes actually move (see
+//! [`crate::transport::Transport`]), defaulting to a plain [`UdpTransport`] so existing callers
+//! are unaffected; [`RtpSender::with_transport`]/[`RtpReceiver::with_transport`] opt into a
+//! different one, e.g. [`crate::transport::EncryptedTransport`] for a link that shouldn't be sent
+//! in cleartext.
 
 use std::{
     fmt::Debug,
@@ -21,15 +69,386 @@ use std::{
     num::NonZero,
     ops::{Deref, DerefMut},
     sync::{Arc, Mutex, MutexGuard},
+    time::Instant,
 };
 
-use zerocopy::{byteorder::network_endian::U32, FromBytes, Unaligned};
+use zerocopy::{byteorder::network_endian::{U16, U32}, FromBytes, Unaligned};
 use zerocopy::{Immutable, IntoBytes, KnownLayout, TryFromBytes};
 
-#[derive(Debug, FromBytes, IntoBytes, KnownLayout, Immutable, Unaligned)]
+use crate::transport::{Transport, UdpTransport};
+
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, KnownLayout, Immutable, Unaligned)]
 #[repr(C)]
 pub struct PacketHeader {
-    sequence_number: U32,
+    pub sequence_number: U32,
+    /// A media timestamp supplied by the caller of [`RtpSender::send_bytes`] — e.g. an audio
+    /// sample count or a video capture clock in milliseconds — monotonic per stream but otherwise
+    /// opaque to this module, mirroring RTP's sender-stamped media clock (RFC 3550 5.1). Jitter
+    /// and clock-drift estimation on the receive side assume this is in the same units as
+    /// [`std::time::Instant`] milliseconds; callers that want those estimates to mean anything
+    /// should keep stamping it that way.
+    pub timestamp: U32,
+    /// Synchronization source identifier, distinguishing streams that share one socket/port
+    /// (e.g. audio vs. video), mirroring RTP's SSRC (RFC 3550 5.1). [`RtpReceiver::new`]'s
+    /// `expected_ssrc` makes [`accept_thread`] drop any packet whose SSRC doesn't match before it
+    /// ever reaches the circular buffer.
+    pub ssrc: U32,
+    /// Identifies the payload's codec/format (RFC 3550 5.1); purely informational to this module.
+    pub payload_type: u8,
+    /// Packed flags; currently just the marker bit (bit 0). See [`Self::marker`].
+    flags: u8,
+    /// RFC 1071 one's-complement checksum over the whole packet (header, with this field zeroed,
+    /// plus payload), guarding against corrupted UDP payloads that zerocopy would otherwise
+    /// blindly reinterpret. Written by [`RtpSender::send_bytes`] and verified by [`accept_thread`],
+    /// which discards the packet on mismatch rather than handing corrupt bytes to the buffer.
+    checksum: U16,
+    /// Sequence number of the first data packet in this packet's forward-error-correction group,
+    /// if any. Meaningless when [`Self::fec_group_size`] is `0` (FEC disabled for this packet).
+    /// See [`RtpSender::set_fec_group_size`].
+    fec_group_id: U32,
+    /// Number of data packets (not counting the parity packet itself) in this packet's FEC group,
+    /// or `0` if this packet wasn't sent with FEC enabled. The group's data packets are the
+    /// `fec_group_size` consecutive sequence numbers starting at [`Self::fec_group_id`]; the
+    /// parity packet (see [`Self::is_fec_parity`]) is the one right after them.
+    fec_group_size: u8,
+}
+
+impl PacketHeader {
+    const MARKER_BIT: u8 = 0b1;
+    const FRAGMENT_START_BIT: u8 = 0b10;
+    const FRAGMENT_END_BIT: u8 = 0b100;
+    const FEC_PARITY_BIT: u8 = 0b1000;
+
+    /// The RTP-style marker bit, conventionally "this is the last packet of a frame" (see
+    /// [`RtpSender::send_bytes`]).
+    pub fn marker(&self) -> bool {
+        self.flags & Self::MARKER_BIT != 0
+    }
+
+    fn set_marker(&mut self, marker: bool) {
+        if marker {
+            self.flags |= Self::MARKER_BIT;
+        } else {
+            self.flags &= !Self::MARKER_BIT;
+        }
+    }
+
+    /// Whether this packet is the first fragment of a (possibly multi-packet) logical payload,
+    /// mirroring the start bit of an aggregation-header payloader. See
+    /// [`RtpSender::send_fragmented`] and [`RtpCircularBuffer::reassemble_frame`]. A payload that
+    /// fit in a single packet has both this and [`Self::fragment_end`] set — it's trivially both
+    /// the start and the end of its own one-packet frame.
+    pub fn fragment_start(&self) -> bool {
+        self.flags & Self::FRAGMENT_START_BIT != 0
+    }
+
+    /// Whether this packet is the last fragment of its logical payload. See [`Self::fragment_start`].
+    pub fn fragment_end(&self) -> bool {
+        self.flags & Self::FRAGMENT_END_BIT != 0
+    }
+
+    fn set_fragment_start(&mut self, fragment_start: bool) {
+        if fragment_start {
+            self.flags |= Self::FRAGMENT_START_BIT;
+        } else {
+            self.flags &= !Self::FRAGMENT_START_BIT;
+        }
+    }
+
+    fn set_fragment_end(&mut self, fragment_end: bool) {
+        if fragment_end {
+            self.flags |= Self::FRAGMENT_END_BIT;
+        } else {
+            self.flags &= !Self::FRAGMENT_END_BIT;
+        }
+    }
+
+    /// Whether this packet is the parity packet of its FEC group (its payload is a byte-wise XOR
+    /// of the group's data payloads, not real data) rather than one of the group's data packets.
+    /// Meaningless when [`Self::fec_group_size`] is `0`.
+    pub fn is_fec_parity(&self) -> bool {
+        self.flags & Self::FEC_PARITY_BIT != 0
+    }
+
+    fn set_fec_parity(&mut self, is_parity: bool) {
+        if is_parity {
+            self.flags |= Self::FEC_PARITY_BIT;
+        } else {
+            self.flags &= !Self::FEC_PARITY_BIT;
+        }
+    }
+
+    /// See [`Self::fec_group_id`] field doc.
+    pub fn fec_group_id(&self) -> u32 {
+        self.fec_group_id.into()
+    }
+
+    /// See [`Self::fec_group_size`] field doc.
+    pub fn fec_group_size(&self) -> u8 {
+        self.fec_group_size
+    }
+
+    fn set_fec_group(&mut self, group_id: u32, group_size: u8, is_parity: bool) {
+        self.fec_group_id = group_id.into();
+        self.fec_group_size = group_size;
+        self.set_fec_parity(is_parity);
+    }
+}
+
+/// An RFC 1071 one's-complement checksum accumulator, used to guard packets against UDP payload
+/// corruption that zerocopy would otherwise blindly reinterpret as valid data.
+struct Checksum {
+    sum: u32,
+}
+
+impl Checksum {
+    fn new() -> Self {
+        Checksum { sum: 0 }
+    }
+
+    /// Folds `bytes` into the running sum, two bytes at a time as big-endian 16-bit words. An odd
+    /// trailing byte is padded with a zero low byte, per RFC 1071 4.1.
+    fn add(&mut self, bytes: &[u8]) {
+        let mut chunks = bytes.chunks_exact(2);
+        for chunk in &mut chunks {
+            self.sum += u16::from_be_bytes([chunk[0], chunk[1]]) as u32;
+        }
+        if let [last] = chunks.remainder() {
+            self.sum += u16::from_be_bytes([*last, 0]) as u32;
+        }
+    }
+
+    /// Folds carries out of the high 16 bits and returns the bitwise-NOT of the result, per
+    /// RFC 1071 4.1.
+    fn finish(mut self) -> u16 {
+        while self.sum >> 16 != 0 {
+            self.sum = (self.sum >> 16) + (self.sum & 0xFFFF);
+        }
+        !(self.sum as u16)
+    }
+}
+
+/// Computes the RFC 1071 checksum of `packet` as it would appear on the wire, i.e. with the
+/// header's `checksum` field treated as zero.
+fn compute_checksum(packet: &[u8]) -> u16 {
+    let checksum_offset = offset_of!(PacketHeader, checksum);
+
+    let mut checksum = Checksum::new();
+    checksum.add(&packet[..checksum_offset]);
+    checksum.add(&[0, 0]);
+    checksum.add(&packet[checksum_offset + 2..]);
+    checksum.finish()
+}
+
+/// A receiver-side summary of a stream's health, computed from the sequence numbers and
+/// timestamps observed in [`RtpCircularBuffer`]. Mirrors the bookkeeping in an RTCP receiver
+/// report (RFC 3550 6.4.1), but is exchanged however the caller likes (e.g. piggybacked on the
+/// existing control-message channel) rather than as a literal RTCP packet.
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, KnownLayout, Immutable, Unaligned)]
+#[repr(C)]
+pub struct ReceiverReport {
+    /// First sequence number ever observed on this stream.
+    base_seq: U32,
+    /// Highest sequence number observed, extended with the wraparound cycle count.
+    extended_highest_seq: U32,
+    /// Total packets expected minus total packets actually received, over the whole stream.
+    cumulative_lost: U32,
+    /// Fraction of packets lost since the previous report, as an 8-bit fixed-point fraction
+    /// of 256 (so 128 means roughly 50% loss over the interval).
+    fraction_lost: u8,
+    _pad: [u8; 3],
+    /// Smoothed interarrival jitter estimate, in the same clock units as [`PacketHeader::timestamp`].
+    jitter: U32,
+}
+
+impl ReceiverReport {
+    /// Fraction of packets lost over the last reporting interval, as a value in `[0.0, 1.0]`.
+    pub fn loss_ratio(&self) -> f64 {
+        self.fraction_lost as f64 / 256.0
+    }
+
+    pub fn cumulative_lost(&self) -> u32 {
+        self.cumulative_lost.into()
+    }
+
+    pub fn jitter(&self) -> u32 {
+        self.jitter.into()
+    }
+
+    /// Scales a sender-side budget (e.g. `PACKET_SEND_THRESHOLD`, or a JPEG quality level) down
+    /// under observed loss, leaving it untouched on a clean link. Never scales below 25% of
+    /// `budget`, so a single bad report interval can't stall the stream entirely.
+    pub fn scale_budget(&self, budget: usize) -> usize {
+        let scale = 1.0 - 0.75 * self.loss_ratio();
+        ((budget as f64) * scale).round() as usize
+    }
+}
+
+/// Running tallies used to compute a [`ReceiverReport`] on demand.
+/// Lives alongside [`RtpCircularBuffer`] so it can be updated from the same lock as packets
+/// arrive, without adding another mutex to the receive path.
+#[derive(Debug)]
+struct ReceiverReportStats {
+    start: Instant,
+    base_seq: Option<u32>,
+    max_seq: u32,
+    cycles: u32,
+    packets_received: u64,
+    /// `(expected, received)` as of the last time a report was taken, to compute the
+    /// interval-local fraction lost rather than the cumulative one.
+    expected_at_last_report: u32,
+    received_at_last_report: u64,
+    /// Smoothed interarrival jitter estimate (RFC 3550 6.4.1), in sender clock units.
+    jitter: f64,
+    /// `arrival_time - packet_timestamp` for the previous packet, used to compute jitter's `D` term.
+    last_transit: Option<i64>,
+}
+
+impl ReceiverReportStats {
+    fn new() -> Self {
+        ReceiverReportStats {
+            start: Instant::now(),
+            base_seq: None,
+            max_seq: 0,
+            cycles: 0,
+            packets_received: 0,
+            expected_at_last_report: 0,
+            received_at_last_report: 0,
+            jitter: 0.0,
+            last_transit: None,
+        }
+    }
+
+    /// Records a just-accepted packet's sequence number and sender timestamp.
+    fn on_packet_received(&mut self, seq_num: u32, packet_timestamp: u32) {
+        if self.base_seq.is_none() {
+            self.base_seq = Some(seq_num);
+            self.max_seq = seq_num;
+        } else if seq_num.wrapping_sub(self.max_seq) < u32::MAX / 2 {
+            // seq_num is ahead of max_seq (possibly having wrapped past it).
+            if seq_num < self.max_seq {
+                self.cycles += 1;
+            }
+            self.max_seq = seq_num;
+        }
+        self.packets_received += 1;
+
+        let arrival = self.start.elapsed().as_millis() as i64;
+        let transit = arrival - packet_timestamp as i64;
+        if let Some(last_transit) = self.last_transit {
+            let d = (transit - last_transit).abs() as f64;
+            self.jitter += (d - self.jitter) / 16.0;
+        }
+        self.last_transit = Some(transit);
+    }
+
+    /// The current smoothed interarrival jitter estimate, in sender clock units (milliseconds).
+    fn jitter_millis(&self) -> f64 {
+        self.jitter
+    }
+
+    fn extended_highest_seq(&self) -> u32 {
+        (self.cycles << 16) | (self.max_seq & 0xFFFF)
+    }
+
+    fn expected(&self) -> u32 {
+        let base_seq = self.base_seq.unwrap_or(0);
+        self.extended_highest_seq().wrapping_sub(base_seq).wrapping_add(1)
+    }
+
+    fn take_report(&mut self) -> ReceiverReport {
+        let expected = self.expected();
+        let cumulative_lost = expected.saturating_sub(self.packets_received as u32);
+
+        let expected_interval = expected.wrapping_sub(self.expected_at_last_report);
+        let received_interval = self.packets_received.wrapping_sub(self.received_at_last_report);
+        let fraction_lost = if expected_interval == 0 || (received_interval as u32) >= expected_interval {
+            0
+        } else {
+            (((expected_interval - received_interval as u32) as u64 * 256) / expected_interval as u64) as u8
+        };
+        self.expected_at_last_report = expected;
+        self.received_at_last_report = self.packets_received;
+
+        ReceiverReport {
+            base_seq: self.base_seq.unwrap_or(0).into(),
+            extended_highest_seq: self.extended_highest_seq().into(),
+            cumulative_lost: cumulative_lost.into(),
+            fraction_lost,
+            _pad: [0; 3],
+            jitter: (self.jitter as u32).into(),
+        }
+    }
+}
+
+/// Default multiple of the observed interarrival jitter used to size the adaptive jitter-buffer
+/// target in [`RtpCircularBuffer::target_buffer_span`]. Larger values ride out more turbulence at
+/// the cost of more latency. See [`RtpCircularBuffer::set_jitter_buffer_params`] to tune this per
+/// stream instead of taking the default.
+const DEFAULT_JITTER_BUFFER_SCALE: f64 = 4.0;
+
+/// Default floor on the adaptive jitter-buffer target, in packets, even on a perfectly calm link.
+pub const JITTER_BUFFER_MIN_PACKETS: u32 = 2;
+
+/// Default ceiling on the adaptive jitter-buffer target, in packets, so a single burst of loss or
+/// jitter can't make playback wait indefinitely.
+pub const JITTER_BUFFER_MAX_PACKETS: u32 = 32;
+
+/// Time constant of [`ClockDriftSmoother`]'s exponential filter: how quickly the offset estimate
+/// reacts to genuine sender/receiver clock drift, in seconds.
+const CLOCK_DRIFT_TAU_SECS: f64 = 2.0;
+
+/// Largest per-update change [`ClockDriftSmoother`] allows, in milliseconds, so a single outlier
+/// packet (delayed by a one-off reordering or scheduling hiccup) can't jerk the estimate off course.
+const CLOCK_DRIFT_MAX_STEP_MILLIS: f64 = 50.0;
+
+/// Tracks the offset between a sender's [`PacketHeader::timestamp`] clock and this receiver's own
+/// clock, smoothed over time. The raw, per-packet offset is noisy (scheduling jitter, network
+/// jitter), so playback loops shouldn't use it directly; the smoothed value lets a packet's
+/// timestamp be converted into a stable local target time instead of inferring freshness purely
+/// from how full [`RtpCircularBuffer`] looks (i.e. [`RtpCircularBuffer::early_latest_span`]).
+#[derive(Debug)]
+struct ClockDriftSmoother {
+    start: Instant,
+    /// Smoothed `received_time - packet_timestamp` offset, in milliseconds. `None` until the
+    /// first sample seeds it.
+    smoothed_offset: Option<f64>,
+    last_update: Option<Instant>,
+}
+
+impl ClockDriftSmoother {
+    fn new() -> Self {
+        ClockDriftSmoother {
+            start: Instant::now(),
+            smoothed_offset: None,
+            last_update: None,
+        }
+    }
+
+    /// Records a just-accepted packet's sender timestamp and folds it into the smoothed offset.
+    fn on_packet_received(&mut self, packet_timestamp: u32) {
+        let now = Instant::now();
+        let raw_offset = self.start.elapsed().as_millis() as f64 - packet_timestamp as f64;
+
+        self.smoothed_offset = Some(match (self.smoothed_offset, self.last_update) {
+            (Some(smoothed), Some(last_update)) => {
+                let dt = now.duration_since(last_update).as_secs_f64();
+                let alpha = 1.0 - (-dt / CLOCK_DRIFT_TAU_SECS).exp();
+                let step = ((raw_offset - smoothed) * alpha)
+                    .clamp(-CLOCK_DRIFT_MAX_STEP_MILLIS, CLOCK_DRIFT_MAX_STEP_MILLIS);
+                smoothed + step
+            }
+            // Seed the estimate with the first sample; there's nothing yet to smooth against.
+            _ => raw_offset,
+        });
+        self.last_update = Some(now);
+    }
+
+    /// The smoothed `received_time - packet_timestamp` offset, in milliseconds. `None` until the
+    /// first packet has been received.
+    fn offset_millis(&self) -> Option<f64> {
+        self.smoothed_offset
+    }
 }
 
 #[derive(Debug, TryFromBytes, IntoBytes, KnownLayout, Immutable)]
@@ -106,40 +525,79 @@ where
     }
 }
 
-/// A packet buffer slot. See [`RtpCircularBuffer`].
-/// The `PACKET_SLOT_SIZE` is the size of the packet slot in bytes. This size **is not inclusive** of packet metadata.
-pub struct MaybeInitPacket<
-    Payload: TryFromBytes + IntoBytes + KnownLayout + Immutable + ?Sized,
+/// A correctly-aligned, fixed-size byte arena shared by every slot of one [`RtpCircularBuffer`].
+/// Packets are packed into it back-to-back at variable offsets (see
+/// [`RtpCircularBuffer::store_packet`]) instead of each slot owning its own `SLOT_SIZE`-sized
+/// array, the way [`AlignedPacketBytes`] aligns a single packet slot for a sender's scratch
+/// buffer — just sized for the whole ring's worst case (`SLOT_SIZE * BUFFER_LENGTH` bytes) at
+/// once, up front.
+struct AlignedArena<
     AlignPayloadTo: TryFromBytes + IntoBytes + KnownLayout + Immutable,
-    const SLOT_SIZE: usize,
-> where
-    [(); size_of_packet::<[u8; SLOT_SIZE]>()]: Sized,
-{
+    const ARENA_BYTES: usize,
+> {
+    _align: [Packet<AlignPayloadTo>; 0], // align to the alignment of the packet
+    inner: [u8; ARENA_BYTES],
+}
+
+/// A metadata ring entry for [`RtpCircularBuffer`]: whether a packet is buffered for this
+/// sequence-number slot, and if so, where its bytes live in the shared [`AlignedArena`]. This
+/// used to embed a whole `SLOT_SIZE`-sized byte array per slot; now it's just a few words, so
+/// deepening the reorder window (see [`RtpCircularBuffer::set_target_capacity`]) no longer also
+/// multiplies how much payload memory is reserved.
+#[derive(Debug, Clone, Copy)]
+struct PacketMeta {
     /// Size of the received packet. Is None if the packet is not initialized.
     recv_size: Option<NonZero<usize>>,
-    // align to the alignment of the packet
-    packet: AlignedPacketBytes<Payload, AlignPayloadTo, SLOT_SIZE>,
+    /// Byte offset into the arena where this packet's bytes start. Meaningful only when
+    /// `recv_size` is `Some`.
+    arena_offset: usize,
+    /// Arena bytes this slot accounts for, including any wrap-boundary padding that was inserted
+    /// ahead of it (see [`RtpCircularBuffer::store_packet`]). Subtracted from
+    /// [`RtpCircularBuffer::arena_used`] on consume so the allocator's bookkeeping stays exact.
+    arena_reserved: usize,
 }
 
-impl<
-        Payload: TryFromBytes + IntoBytes + KnownLayout + Immutable + ?Sized,
-        AlignPayloadTo: TryFromBytes + IntoBytes + KnownLayout + Immutable,
-        const SLOT_SIZE: usize,
-    > MaybeInitPacket<Payload, AlignPayloadTo, SLOT_SIZE>
-where
-    [(); size_of_packet::<[u8; SLOT_SIZE]>()]: Sized,
-{
-    pub fn is_init(&self) -> bool {
+impl PacketMeta {
+    const EMPTY: PacketMeta = PacketMeta {
+        recv_size: None,
+        arena_offset: 0,
+        arena_reserved: 0,
+    };
+
+    fn is_init(&self) -> bool {
         self.recv_size.is_some()
     }
+}
 
-    pub fn get_data(&self) -> Option<&Packet<Payload>> {
-        if let Some(len) = self.recv_size {
-            Some(Packet::<Payload>::try_ref_from_bytes(&self.packet[..len.into()]).unwrap())
-        } else {
-            None
-        }
-    }
+/// A snapshot of [`RtpCircularBuffer`]'s occupancy, analogous to a TCP socket's buffer limits
+/// (`SO_RCVBUF` vs. bytes actually queued). `target_capacity` and `capacity` are reported
+/// separately because [`RtpCircularBuffer::set_target_capacity`] is synchronous — they're always
+/// equal right after a resize — but keeping them distinct leaves room for a future resize path
+/// that can't apply immediately (e.g. one that has to wait out an in-flight reassembly) without
+/// changing this struct's shape.
+#[derive(Debug, Clone, Copy)]
+pub struct BufferLimits {
+    /// Number of slots in the reorder window currently holding a received packet.
+    pub occupied: usize,
+    /// Number of slots the reorder window is currently backed by.
+    pub capacity: usize,
+    /// Number of slots [`RtpCircularBuffer::set_target_capacity`] was last asked for.
+    pub target_capacity: usize,
+    /// See [`RtpCircularBuffer::early_latest_span`].
+    pub early_latest_span: u32,
+    /// Current smoothed interarrival jitter estimate, in milliseconds. See
+    /// [`RtpCircularBuffer::target_buffer_span`], which is derived from this.
+    pub jitter_millis: f64,
+}
+
+/// What [`RtpCircularBuffer::diagnose_gap`] found when the earliest slot wasn't ready to play.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayoutGap {
+    /// Still within the playout delay, or genuinely the newest packet seen so far — keep waiting.
+    NotReady,
+    /// The earliest slot never arrived, but a later one did: a true loss rather than ordinary
+    /// earliness. See [`RtpCircularBuffer::skip_lost_packet`].
+    Lost,
 }
 
 /// A circular buffer of RTP packets.
@@ -158,7 +616,45 @@ pub struct RtpCircularBuffer<
     /// The span of the earliest sequence number and the latest sequence number of a received packet in the buffer.
     /// This can relied on as a hint for how full the buffer is. (i.e. how ahead is the latest received packet?)
     early_latest_span: u32,
-    buf: Box<[MaybeInitPacket<Payload, AlignPayloadTo, SLOT_SIZE>; BUFFER_LENGTH]>,
+    /// Metadata ring for the reorder window, indexed by `seq_num % meta.len()`. A `Vec` rather
+    /// than the `[_; BUFFER_LENGTH]` array this started as, so [`Self::set_target_capacity`] can
+    /// grow or shrink it at runtime; `BUFFER_LENGTH` only supplies the initial capacity. Each
+    /// entry is a few words, not a `SLOT_SIZE`-sized array, since payload bytes live in `arena`
+    /// instead — so deepening this ring doesn't multiply payload memory along with it.
+    meta: Vec<PacketMeta>,
+    /// Last capacity requested via [`Self::set_target_capacity`], tracked separately from
+    /// `meta.len()` even though the two are always equal today — see [`BufferLimits`].
+    target_capacity: usize,
+    /// Shared, fixed-size payload storage every `meta` entry's bytes are packed into at a
+    /// variable offset. Sized once, at construction, to `SLOT_SIZE * BUFFER_LENGTH` bytes — the
+    /// same worst-case ceiling the old one-array-per-slot layout reserved unconditionally, except
+    /// now it's genuinely a ceiling: actual usage tracks real packet sizes, which is what lets
+    /// `meta` be resized independently of it. See [`Self::store_packet`].
+    arena: Box<AlignedArena<AlignPayloadTo, { SLOT_SIZE * BUFFER_LENGTH }>>,
+    /// Byte offset into `arena.inner` that [`Self::store_packet`] will write to next; always in
+    /// range, reset to `0` when a payload would not fit before the end of the arena.
+    arena_write_cursor: usize,
+    /// Bytes of `arena` currently accounted for by some `meta` entry, including wrap-boundary
+    /// padding (see [`PacketMeta::arena_reserved`]). Always `<= arena.inner.len()`.
+    arena_used: usize,
+    report_stats: ReceiverReportStats,
+    clock_drift: ClockDriftSmoother,
+    /// The only [`PacketHeader::ssrc`] [`accept_thread`] will accept packets from; anything else
+    /// is assumed to be a different stream sharing the same socket/port and is dropped before it
+    /// reaches this buffer. See [`RtpReceiver::new`].
+    expected_ssrc: u32,
+    /// The stream's nominal packet spacing, in milliseconds, used to turn the jitter estimate into
+    /// a playout-delay gate for [`Self::peek_earliest_packet`]/[`Self::consume_earliest_packet`].
+    /// `None` (the default) disables the gate: a slot is handed out as soon as it's filled, same
+    /// as before this existed. See [`Self::set_packet_interval_millis`].
+    packet_interval_millis: Option<f64>,
+    /// Multiple of the observed interarrival jitter used by [`Self::target_buffer_span`]. See
+    /// [`Self::set_jitter_buffer_params`].
+    jitter_buffer_scale: f64,
+    /// Floor on [`Self::target_buffer_span`], in packets. See [`Self::set_jitter_buffer_params`].
+    jitter_buffer_min: u32,
+    /// Ceiling on [`Self::target_buffer_span`], in packets. See [`Self::set_jitter_buffer_params`].
+    jitter_buffer_max: u32,
 }
 
 /// A packet that has been received and is ready to be consumed.
@@ -184,18 +680,11 @@ where
 {
     pub fn get_data(&self) -> Option<&Packet<Payload>> {
         let rtp_receiver = &self.0;
-
-        if let Some(MaybeInitPacket {
-            recv_size: Some(packet_len),
-            packet: p,
-            ..
-        }) = rtp_receiver.get(rtp_receiver.earliest_seq)
-        {
-            log::trace!("Getting data from seq {} with len {}", rtp_receiver.earliest_seq, packet_len);
-            Some(Packet::<Payload>::try_ref_from_bytes(&p[..((*packet_len).into())]).unwrap())
-        } else {
-            None
+        let data = rtp_receiver.get_data(rtp_receiver.earliest_seq);
+        if data.is_some() {
+            log::trace!("Getting data from seq {}", rtp_receiver.earliest_seq);
         }
+        data
     }
 }
 
@@ -211,10 +700,13 @@ where
     fn drop(&mut self) {
         let rtp_receiver = &mut self.0;
 
-        rtp_receiver
-            .get_mut(rtp_receiver.earliest_seq)
-            .unwrap()
-            .recv_size = None;
+        let earliest = rtp_receiver.earliest_seq;
+        let freed_arena_bytes = match rtp_receiver.get_mut(earliest) {
+            Some(slot) if slot.recv_size.take().is_some() => std::mem::take(&mut slot.arena_reserved),
+            _ => 0,
+        };
+        rtp_receiver.arena_used -= freed_arena_bytes;
+
         log::trace!("consumed seq {}", rtp_receiver.earliest_seq);
         rtp_receiver.earliest_seq = rtp_receiver.earliest_seq.wrapping_add(1);
         rtp_receiver.early_latest_span = rtp_receiver.early_latest_span.saturating_sub(1);
@@ -230,38 +722,286 @@ impl<
 where
     [(); size_of_packet::<[u8; SLOT_SIZE]>()]: Sized,
 {
-    const fn generate_default_packet() -> MaybeInitPacket<Payload, AlignPayloadTo, SLOT_SIZE> {
-        MaybeInitPacket {
-            recv_size: None,
-            packet: AlignedPacketBytes {
-                _phantom: PhantomData,
+    fn new(expected_ssrc: u32) -> Self {
+        RtpCircularBuffer {
+            earliest_seq: 0,
+            early_latest_span: 0,
+            meta: vec![PacketMeta::EMPTY; BUFFER_LENGTH],
+            target_capacity: BUFFER_LENGTH,
+            arena: Box::new(AlignedArena {
                 _align: [],
-                inner: [0u8; size_of_packet::<[u8; SLOT_SIZE]>()],
-            },
+                inner: [0u8; SLOT_SIZE * BUFFER_LENGTH],
+            }),
+            arena_write_cursor: 0,
+            arena_used: 0,
+            report_stats: ReceiverReportStats::new(),
+            clock_drift: ClockDriftSmoother::new(),
+            expected_ssrc,
+            packet_interval_millis: None,
+            jitter_buffer_scale: DEFAULT_JITTER_BUFFER_SCALE,
+            jitter_buffer_min: JITTER_BUFFER_MIN_PACKETS,
+            jitter_buffer_max: JITTER_BUFFER_MAX_PACKETS,
         }
     }
 
-    fn new() -> Self {
-        RtpCircularBuffer {
-            earliest_seq: 0,
-            early_latest_span: 0,
-            buf: Box::new([const { Self::generate_default_packet() }; BUFFER_LENGTH]),
+    /// Computes a [`ReceiverReport`] summarizing loss and jitter since the last call to this
+    /// function (or since the buffer was created, for the first call).
+    pub fn take_receiver_report(&mut self) -> ReceiverReport {
+        self.report_stats.take_report()
+    }
+
+    /// The smoothed offset between the sender's timestamp clock and this receiver's own clock, in
+    /// milliseconds (see [`ClockDriftSmoother`]). `None` until the first packet has arrived.
+    /// Add this to a packet's [`PacketHeader::timestamp`] to get a stable local target time.
+    pub fn clock_drift_offset_millis(&self) -> Option<f64> {
+        self.clock_drift.offset_millis()
+    }
+
+    /// Adaptive jitter-buffer depth target, in packets: callers should wait for
+    /// [`RtpCircularBuffer::early_latest_span`] to reach this before draining, instead of a fixed
+    /// constant. Grows with observed interarrival jitter (scaled by this buffer's jitter-buffer
+    /// scale) so a turbulent link gets more cushion, and shrinks toward its configured minimum on
+    /// a calm one, clamped to `[min, max]`. See [`Self::set_jitter_buffer_params`] to tune those
+    /// away from their defaults.
+    ///
+    /// `packet_interval_millis` is the stream's nominal spacing between packets (e.g. an audio
+    /// stream's sample-count-over-frequency, or a video stream's `1000.0 / fps`), used to convert a
+    /// jitter estimate in milliseconds into a depth in packets.
+    pub fn target_buffer_span(&self, packet_interval_millis: f64) -> u32 {
+        let jitter_millis = self.report_stats.jitter_millis();
+        let target = (self.jitter_buffer_scale * jitter_millis / packet_interval_millis).ceil();
+        let target = (target as u32).clamp(self.jitter_buffer_min, self.jitter_buffer_max);
+        log::debug!(
+            "jitter buffer target depth: {target} packets (jitter {jitter_millis:.2}ms, scale {})",
+            self.jitter_buffer_scale
+        );
+        target
+    }
+
+    /// Tunes [`Self::target_buffer_span`]'s latency-vs-smoothness tradeoff: `min`/`max` clamp the
+    /// reported depth (in packets), and `scale` is the multiple of observed interarrival jitter
+    /// used to grow it under turbulence. Defaults to [`JITTER_BUFFER_MIN_PACKETS`],
+    /// [`JITTER_BUFFER_MAX_PACKETS`] and [`DEFAULT_JITTER_BUFFER_SCALE`] until called.
+    pub fn set_jitter_buffer_params(&mut self, min: u32, max: u32, scale: f64) {
+        assert!(min <= max, "jitter buffer min ({min}) must not exceed max ({max})");
+        self.jitter_buffer_min = min;
+        self.jitter_buffer_max = max;
+        self.jitter_buffer_scale = scale;
+    }
+
+    /// Configures this stream's nominal packet spacing, enabling the adaptive playout-delay gate
+    /// on [`Self::peek_earliest_packet`]/[`Self::consume_earliest_packet`] (see
+    /// [`Self::target_buffer_span`] for what the spacing is used for). Until this is called, those
+    /// methods release a slot as soon as it's filled, with no de-jittering hold.
+    pub fn set_packet_interval_millis(&mut self, packet_interval_millis: f64) {
+        self.packet_interval_millis = Some(packet_interval_millis);
+    }
+
+    /// The playout delay, in packets, the earliest slot must clear before it's handed to a caller
+    /// — `0` if no [`Self::set_packet_interval_millis`] has been configured.
+    fn playout_delay_packets(&self) -> u32 {
+        self.packet_interval_millis
+            .map_or(0, |interval| self.target_buffer_span(interval))
+    }
+
+    /// Whether the earliest slot should be handed to a caller: it's filled, and either the
+    /// adaptive playout delay has elapsed or buffer pressure (the window is almost full) forces
+    /// early release so a turbulent sender can't stall playback indefinitely.
+    fn earliest_ready(&self) -> bool {
+        let Some(slot) = self.get(self.earliest_seq) else {
+            return false;
+        };
+        if !slot.is_init() {
+            return false;
         }
+        let under_pressure = self.early_latest_span as usize >= self.meta.len() - 1;
+        under_pressure || self.early_latest_span >= self.playout_delay_packets()
     }
 
-    /// Returns the slot with the earliest seq_num in the circular buffer.
-    /// Note that this slot may or may not contain a packet.
-    /// The slot will be consumed upon dropping the returned value.
-    pub fn consume_earliest_packet(
+    /// Returns the slot with the earliest seq_num in the circular buffer, unconditionally —
+    /// bypassing the playout-delay gate in [`Self::consume_earliest_packet`]. Used internally for
+    /// bookkeeping (e.g. [`accept_thread`] dropping stale slots to make room) where "is this ready
+    /// for playback" doesn't apply.
+    fn consume_earliest_packet_unchecked(
         &mut self,
     ) -> ReceivedPacket<'_, Payload, AlignPayloadTo, SLOT_SIZE, BUFFER_LENGTH> {
         ReceivedPacket(self)
     }
 
-    /// Returns a reference to the slot with the earliest seq_num in the buffer.
-    /// Returns None if the slot is not inhabited by a packet.
+    /// Returns the slot with the earliest seq_num in the circular buffer, consuming it — but only
+    /// once [`Self::earliest_ready`] says it's time, acting as a de-jittering stage on top of the
+    /// raw reorder buffer. Returns `None` without consuming anything otherwise.
+    pub fn consume_earliest_packet(
+        &mut self,
+    ) -> Option<ReceivedPacket<'_, Payload, AlignPayloadTo, SLOT_SIZE, BUFFER_LENGTH>> {
+        self.earliest_ready().then(|| self.consume_earliest_packet_unchecked())
+    }
+
+    /// Returns a reference to the slot with the earliest seq_num in the buffer, if
+    /// [`Self::earliest_ready`] says it's time to release it.
     pub fn peek_earliest_packet(&self) -> Option<&Packet<Payload>> {
-        self.get(self.earliest_seq).and_then(|p| p.get_data())
+        if !self.earliest_ready() {
+            return None;
+        }
+        self.get_data(self.earliest_seq)
+    }
+
+    /// Diagnoses why the earliest slot isn't ready, for a caller that just got `None` back from
+    /// [`Self::consume_earliest_packet`]/[`Self::peek_earliest_packet`] and needs to tell "still
+    /// waiting out the playout delay" apart from "this packet is never coming" — the latter is a
+    /// true loss (a later slot has already arrived) rather than ordinary earliness, and is worth
+    /// concealing instead of stalling playback on.
+    pub fn diagnose_gap(&self) -> PlayoutGap {
+        let earliest_filled = self.get(self.earliest_seq).is_some_and(PacketMeta::is_init);
+        if earliest_filled {
+            PlayoutGap::NotReady
+        } else if self.early_latest_span > 0 {
+            PlayoutGap::Lost
+        } else {
+            PlayoutGap::NotReady
+        }
+    }
+
+    /// Skips past a slot [`Self::diagnose_gap`] reported as [`PlayoutGap::Lost`], once the caller
+    /// has synthesized its own replacement (e.g. repeat-with-fade for audio, previous-macroblock
+    /// for video) to play in its place. Bookkeeping mirrors [`ReceivedPacket`]'s `Drop`, minus
+    /// freeing arena bytes, since there was never a packet here to free.
+    pub fn skip_lost_packet(&mut self) {
+        self.earliest_seq = self.earliest_seq.wrapping_add(1);
+        self.early_latest_span = self.early_latest_span.saturating_sub(1);
+    }
+
+    /// Attempts to recover one lost data packet of the FEC group `group_id`/`group_size`
+    /// identifies (see [`RtpSender::set_fec_group_size`]), after a just-stored packet belonging to
+    /// it. A no-op unless the group's `group_size` data slots plus its parity slot (the one right
+    /// after them) are all still inside the window, with exactly one slot
+    /// missing and that slot a data slot (not the parity packet itself) — reconstructing the
+    /// parity packet wouldn't help anything downstream, and any other combination of gaps means
+    /// either nothing is missing yet or more than one packet is, which XOR parity can't recover.
+    ///
+    /// The recovered packet's payload is the byte-wise XOR of the rest of the group (zero-padded
+    /// out to the parity packet's own length, which the sender padded to the group's longest
+    /// payload), exactly reversing how [`RtpSender::set_fec_group_size`] built the parity packet.
+    /// Its `timestamp`/`ssrc`/`payload_type` are copied from the parity packet rather than
+    /// recovered the same way, since the XOR only ever covered payload bytes; it's always stamped
+    /// as its own complete one-packet frame (`fragment_start`/`fragment_end` both set), so a
+    /// reconstructed packet can never corrupt a neighboring frame's boundary, though it may itself
+    /// reassemble wrong if the lost packet was actually a middle fragment of a larger one.
+    pub fn try_reconstruct_fec_group(&mut self, group_id: u32, group_size: u8) {
+        if group_size == 0 {
+            return;
+        }
+        let parity_seq = group_id.wrapping_add(group_size as u32);
+
+        let mut missing_seq = None;
+        for offset in 0..=group_size as u32 {
+            let seq = group_id.wrapping_add(offset);
+            let Some(slot) = self.get(seq) else {
+                return; // part of the group has already scrolled out of the window.
+            };
+            if !slot.is_init() {
+                if missing_seq.is_some() {
+                    return; // more than one slot missing; XOR parity can't recover that.
+                }
+                missing_seq = Some(seq);
+            }
+        }
+        let Some(missing_seq) = missing_seq else {
+            return; // the whole group is already present; nothing to reconstruct.
+        };
+        if missing_seq == parity_seq {
+            return; // the parity packet itself was lost; no data packet needs recovering.
+        }
+
+        let parity_packet = self.get_data(parity_seq).expect("checked present above");
+        let timestamp = parity_packet.header.timestamp;
+        let ssrc = parity_packet.header.ssrc;
+        let payload_type = parity_packet.header.payload_type;
+        let mut reconstructed = parity_packet.data.as_bytes().to_vec();
+
+        for offset in 0..group_size as u32 {
+            let seq = group_id.wrapping_add(offset);
+            if seq == missing_seq {
+                continue;
+            }
+            let packet = self.get_data(seq).expect("checked present above");
+            for (r, p) in reconstructed.iter_mut().zip(packet.data.as_bytes()) {
+                *r ^= p;
+            }
+        }
+
+        let packet_start_offset = offset_of!(Packet<AlignPayloadTo>, data);
+        let mut synthetic = vec![0u8; packet_start_offset + reconstructed.len()];
+        {
+            let header = PacketHeader::mut_from_bytes(&mut synthetic[0..size_of::<PacketHeader>()])
+                .unwrap();
+            header.sequence_number = missing_seq.into();
+            header.timestamp = timestamp;
+            header.ssrc = ssrc;
+            header.payload_type = payload_type;
+            header.set_marker(false);
+            header.set_fragment_start(true);
+            header.set_fragment_end(true);
+            header.set_fec_group(0, 0, false);
+            header.checksum = 0.into();
+        }
+        synthetic[packet_start_offset..].copy_from_slice(&reconstructed);
+        let checksum = compute_checksum(&synthetic);
+        PacketHeader::mut_from_bytes(&mut synthetic[0..size_of::<PacketHeader>()])
+            .unwrap()
+            .checksum = checksum.into();
+
+        if self.store_packet(missing_seq, &synthetic) {
+            log::debug!(
+                "reconstructed lost seq {missing_seq} via FEC (group {group_id}, size {group_size})"
+            );
+        }
+    }
+
+    /// Reconstructs the zero-copy [`Packet`] view for `seq_num`'s slot, if it's in the window and
+    /// holds a received packet — slicing it out of the shared [`Self::arena`] by the slot's
+    /// recorded offset/length.
+    fn get_data(&self, seq_num: u32) -> Option<&Packet<Payload>> {
+        let slot = self.get(seq_num)?;
+        let len: usize = slot.recv_size?.into();
+        let bytes = &self.arena.inner[slot.arena_offset..slot.arena_offset + len];
+        Some(Packet::<Payload>::try_ref_from_bytes(bytes).unwrap())
+    }
+
+    /// If the earliest run of slots forms a complete fragmented frame — a
+    /// [`PacketHeader::fragment_start`] packet, followed by contiguous fragments with no gaps, up
+    /// to (and including) a [`PacketHeader::fragment_end`] packet — reassembles and returns the
+    /// frame's payload bytes, consuming every fragment that made it up in the process. A payload
+    /// that [`RtpSender::send_fragmented`] sent in a single packet reassembles trivially here too,
+    /// since that packet is both its own start and end fragment.
+    ///
+    /// Returns `None` without consuming anything if the run isn't complete yet: the earliest slot
+    /// isn't a fragment start, an intermediate fragment hasn't arrived (a gap), or the run reaches
+    /// [`Self::early_latest_span`] without ever seeing a fragment-end packet.
+    pub fn reassemble_frame(&mut self) -> Option<Vec<u8>> {
+        if !self.get_data(self.earliest_seq)?.header.fragment_start() {
+            return None;
+        }
+
+        let mut frame_len = 0u32;
+        loop {
+            if frame_len > self.early_latest_span {
+                return None;
+            }
+            let seq = self.earliest_seq.wrapping_add(frame_len);
+            let packet = self.get_data(seq)?;
+            frame_len += 1;
+            if packet.header.fragment_end() {
+                break;
+            }
+        }
+
+        let mut reassembled = Vec::new();
+        for _ in 0..frame_len {
+            let fragment = self.consume_earliest_packet_unchecked();
+            reassembled.extend_from_slice(fragment.get_data().unwrap().data.as_bytes());
+        }
+        Some(reassembled)
     }
 
     pub fn earliest_seq(&self) -> u32 {
@@ -272,28 +1012,140 @@ where
         self.early_latest_span
     }
 
-    /// Returns a reference to the [`MaybeInitPacket`] slot that corresponds to the given sequence number.
+    /// Occupancy/capacity snapshot for this buffer. See [`BufferLimits`].
+    pub fn limits(&self) -> BufferLimits {
+        BufferLimits {
+            occupied: self.meta.iter().filter(|slot| slot.is_init()).count(),
+            capacity: self.meta.len(),
+            target_capacity: self.target_capacity,
+            early_latest_span: self.early_latest_span,
+            jitter_millis: self.report_stats.jitter_millis(),
+        }
+    }
+
+    /// Resizes the reorder window to `new_capacity` slots, widening it under heavy loss (a wider
+    /// window tolerates more reordering before a late packet falls outside it) or shrinking it to
+    /// reclaim memory once the link is clean.
+    ///
+    /// Already-buffered packets are preserved, re-indexed modulo the new capacity. Shrinking below
+    /// the current occupied span drops the oldest packets first via the same path
+    /// [`Self::consume_earliest_packet`] uses, exactly as if a caller had drained them.
+    pub fn set_target_capacity(&mut self, new_capacity: usize) {
+        assert!(new_capacity > 0, "buffer capacity must be at least 1 slot");
+        self.target_capacity = new_capacity;
+
+        while self.early_latest_span as usize >= new_capacity {
+            self.consume_earliest_packet_unchecked();
+        }
+
+        let mut new_meta = vec![PacketMeta::EMPTY; new_capacity];
+        for offset in 0..self.meta.len() as u32 {
+            let seq = self.earliest_seq.wrapping_add(offset);
+            let old_idx = (seq as usize) % self.meta.len();
+            if self.meta[old_idx].is_init() {
+                let new_idx = (seq as usize) % new_capacity;
+                new_meta[new_idx] = self.meta[old_idx];
+            }
+        }
+        self.meta = new_meta;
+    }
+
+    /// Returns a reference to the [`PacketMeta`] slot that corresponds to the given sequence number.
     /// Returns None if the corresponding packet is not present in the buffer.
-    pub fn get(&self, seq_num: u32) -> Option<&MaybeInitPacket<Payload, AlignPayloadTo, SLOT_SIZE>> {
-        if seq_num.wrapping_sub(self.earliest_seq) as usize >= self.buf.len() {
+    pub fn get(&self, seq_num: u32) -> Option<&PacketMeta> {
+        if seq_num.wrapping_sub(self.earliest_seq) as usize >= self.meta.len() {
             None
         } else {
-            let idx = (seq_num as usize) % self.buf.len();
-            Some(&self.buf[idx])
+            let idx = (seq_num as usize) % self.meta.len();
+            Some(&self.meta[idx])
         }
     }
 
-    fn get_mut(
-        &mut self,
-        seq_num: u32,
-    ) -> Option<&mut MaybeInitPacket<Payload, AlignPayloadTo, SLOT_SIZE>> {
-        if seq_num.wrapping_sub(self.earliest_seq) as usize >= self.buf.len() {
+    fn get_mut(&mut self, seq_num: u32) -> Option<&mut PacketMeta> {
+        if seq_num.wrapping_sub(self.earliest_seq) as usize >= self.meta.len() {
             None
         } else {
-            let idx = (seq_num as usize) % self.buf.len();
-            Some(&mut self.buf[idx])
+            let idx = (seq_num as usize) % self.meta.len();
+            Some(&mut self.meta[idx])
         }
     }
+
+    /// Writes `bytes` (a fully-received datagram) into the shared arena and records its location
+    /// in `seq_num`'s metadata slot, evicting the oldest buffered packets to reclaim arena space if
+    /// necessary. Returns `false` (dropping the packet) if `bytes` can't be stored: either it's
+    /// larger than the entire arena, or making room for it would require evicting the very slot
+    /// `seq_num` itself occupies (which would otherwise corrupt the buffer's own bookkeeping).
+    fn store_packet(&mut self, seq_num: u32, bytes: &[u8]) -> bool {
+        let align = std::mem::align_of::<AlignPayloadTo>();
+        let arena_len = self.arena.inner.len();
+
+        if bytes.len() > arena_len {
+            log::warn!("Dropping packet of {} bytes; arena is only {} bytes", bytes.len(), arena_len);
+            return false;
+        }
+
+        loop {
+            let padded_cursor = self.arena_write_cursor.next_multiple_of(align);
+            let wraps = padded_cursor + bytes.len() > arena_len;
+            let offset = if wraps { 0 } else { padded_cursor };
+            let reserved = if wraps {
+                (arena_len - self.arena_write_cursor) + bytes.len()
+            } else {
+                (padded_cursor - self.arena_write_cursor) + bytes.len()
+            };
+
+            if self.arena_used + reserved <= arena_len {
+                for slot in self.arena.inner[offset..offset + bytes.len()].iter_mut().zip(bytes) {
+                    *slot.0 = *slot.1;
+                }
+                self.arena_write_cursor = offset + bytes.len();
+                self.arena_used += reserved;
+
+                let idx = (seq_num as usize) % self.meta.len();
+                self.meta[idx] = PacketMeta {
+                    recv_size: NonZero::new(bytes.len()),
+                    arena_offset: offset,
+                    arena_reserved: reserved,
+                };
+                return true;
+            }
+
+            if self.earliest_seq == seq_num {
+                log::warn!("Dropping packet {seq_num}; evicting further would free its own slot");
+                return false;
+            }
+            self.consume_earliest_packet_unchecked();
+        }
+    }
+}
+
+/// Candidate payload sizes (in bytes) tried by [`probe_path_mtu`], largest first. `1472` is the
+/// classic "1500-byte Ethernet MTU minus IPv4/UDP headers" figure; the rest back off through the
+/// usual internet path-MTU trouble spots (PPPoE framing, a second IP-in-IP hop, the IPv4 minimum
+/// reassembly size) down to the smallest payload that can still carry a packet header.
+pub const MTU_PROBE_CANDIDATES: &[usize] = &[1472, 1400, 1200, 548];
+
+/// A simple startup-time path-MTU probe: tries sending a dummy zero-filled datagram at each of
+/// `candidates` (largest first) over `sock`, and returns the first size that goes out without an
+/// OS-level send error. This is a coarse heuristic, not RFC 1191 path-MTU discovery proper — it
+/// doesn't set the don't-fragment bit or react to ICMP "fragmentation needed", since `std`'s
+/// `UdpSocket` doesn't expose either — but it's enough to steer [`RtpSender::set_payload_size_limit`]
+/// away from a size the local stack or an immediately adjacent link outright refuses to send.
+/// Falls back to the smallest candidate if every size is rejected.
+pub fn probe_path_mtu(sock: &UdpSocket, candidates: &[usize]) -> usize {
+    let probe_buf = vec![0u8; candidates.iter().copied().max().unwrap_or(0)];
+
+    for &size in candidates {
+        match sock.send(&probe_buf[..size]) {
+            Ok(_) => {
+                log::debug!("path-MTU probe: {size} bytes accepted");
+                return size;
+            }
+            Err(e) => log::debug!("path-MTU probe: {size} bytes rejected ({e})"),
+        }
+    }
+
+    candidates.iter().copied().min().unwrap_or(0)
 }
 
 pub type RtpSizedPayloadSender<Payload: TryFromBytes + IntoBytes + Immutable + KnownLayout> =
@@ -304,40 +1156,96 @@ pub type RtpSlicePayloadSender<
     const MAX_SLICE_LENGTH: usize,
 > = RtpSender<[SlicedPayload], SlicedPayload, { size_of::<SlicedPayload>() * MAX_SLICE_LENGTH }>;
 
-/// An RTP sender that sends packets over the network.
+/// An RTP sender that sends packets over the network. Generic over `Tr` so the framing logic
+/// doesn't care whether packets end up going out over plain UDP or something else (see
+/// [`Transport`]); defaults to [`UdpTransport`], the original (and until now, only) behavior.
 pub struct RtpSender<
     Payload: TryFromBytes + IntoBytes + Immutable + KnownLayout + ?Sized,
     AlignPayloadTo: TryFromBytes + IntoBytes + KnownLayout + Immutable,
     const SLOT_SIZE: usize,
+    Tr: Transport = UdpTransport,
 > where
     [(); size_of_packet::<[u8; SLOT_SIZE]>()]: Sized,
 {
-    sock: UdpSocket,
+    transport: Tr,
     seq_num: u32,
+    /// This sender's fixed SSRC, stamped into every [`PacketHeader`] it writes. See
+    /// [`PacketHeader::ssrc`].
+    ssrc: u32,
+    /// This sender's fixed payload type, stamped into every [`PacketHeader`] it writes. See
+    /// [`PacketHeader::payload_type`].
+    payload_type: u8,
     /// A correctly aligned scratch buffer for writing packet data to.
     scratch: AlignedPacketBytes<Payload, AlignPayloadTo, SLOT_SIZE>,
+    /// Runtime cap on how much of `scratch`'s payload region [`Self::send_bytes`] will actually
+    /// hand to its `fill` closure, negotiated down from `SLOT_SIZE` by
+    /// [`Self::set_payload_size_limit`] instead of being fixed at compile time.
+    payload_size_limit: usize,
+    /// Number of data packets per FEC group, or `0` to disable FEC. See
+    /// [`Self::set_fec_group_size`].
+    fec_group_size: u8,
+    /// Running XOR of the current FEC group's data payloads, and how many of them have gone out
+    /// so far; `None` between groups (including whenever FEC is disabled).
+    fec_group_in_progress: Option<FecGroupInProgress>,
+}
+
+/// [`RtpSender`]'s bookkeeping for the FEC group it's currently accumulating a parity packet for.
+struct FecGroupInProgress {
+    /// Sequence number of the group's first data packet — becomes that group's
+    /// [`PacketHeader::fec_group_id`].
+    base_seq: u32,
+    /// How many of the group's data packets have been folded into `parity` so far.
+    sent: u8,
+    /// Running byte-wise XOR of the group's data payloads, zero-padded out to the longest payload
+    /// seen in the group so far.
+    parity: Vec<u8>,
 }
 
 impl<
         Payload: TryFromBytes + IntoBytes + Immutable + KnownLayout + ?Sized,
         AlignPayloadTo: TryFromBytes + IntoBytes + KnownLayout + Immutable,
         const SLOT_SIZE: usize,
-    > RtpSender<Payload, AlignPayloadTo, SLOT_SIZE>
+    > RtpSender<Payload, AlignPayloadTo, SLOT_SIZE, UdpTransport>
 where
     [(); size_of_packet::<[u8; SLOT_SIZE]>()]: Sized,
 {
-    /// Create a new RTP sender.
-    /// The sender will bind to the given socket.
-    /// The sender will use a scratch buffer of size `max_size` for packet serialization.
-    pub fn new(sock: UdpSocket) -> Self {
+    /// Create a new RTP sender, bound to the given socket and stamping `ssrc`/`payload_type`
+    /// into every packet's [`PacketHeader`] (see those fields' docs). See [`Self::with_transport`]
+    /// to send over something other than plain UDP.
+    pub fn new(sock: UdpSocket, ssrc: u32, payload_type: u8) -> Self {
+        Self::with_transport(UdpTransport::new(sock), ssrc, payload_type)
+    }
+}
+
+impl<
+        Payload: TryFromBytes + IntoBytes + Immutable + KnownLayout + ?Sized,
+        AlignPayloadTo: TryFromBytes + IntoBytes + KnownLayout + Immutable,
+        const SLOT_SIZE: usize,
+        Tr: Transport,
+    > RtpSender<Payload, AlignPayloadTo, SLOT_SIZE, Tr>
+where
+    [(); size_of_packet::<[u8; SLOT_SIZE]>()]: Sized,
+{
+    /// Create a new RTP sender over an arbitrary [`Transport`], stamping `ssrc`/`payload_type`
+    /// into every packet's [`PacketHeader`] (see those fields' docs). Use this instead of
+    /// [`Self::new`] to wrap, e.g., an [`crate::transport::EncryptedTransport`].
+    pub fn with_transport(transport: Tr, ssrc: u32, payload_type: u8) -> Self {
         RtpSender {
-            sock,
+            transport,
             seq_num: 0,
+            ssrc,
+            payload_type,
             scratch: AlignedPacketBytes {
                 _phantom: PhantomData,
                 _align: [],
                 inner: [0u8; size_of_packet::<[u8; SLOT_SIZE]>()],
             },
+            // Starts at the compile-time ceiling; narrow it with `set_payload_size_limit` once
+            // the path's actual MTU is known.
+            payload_size_limit: SLOT_SIZE,
+            // FEC starts disabled; opt in with `set_fec_group_size`.
+            fec_group_size: 0,
+            fec_group_in_progress: None,
         }
     }
 
@@ -346,30 +1254,185 @@ where
         self.seq_num
     }
 
+    /// The payload budget (in bytes, exclusive of [`PacketHeader`]) that [`Self::send_bytes`]
+    /// currently hands to its `fill` closure.
+    pub fn payload_size_limit(&self) -> usize {
+        self.payload_size_limit
+    }
+
+    /// Narrows the payload budget used by [`Self::send_bytes`] down from `SLOT_SIZE`, e.g. after
+    /// [`probe_path_mtu`] or a receiver's [`crate::MtuReport`] indicate the path can't carry
+    /// `SLOT_SIZE`-sized packets without fragmenting. `limit` can't exceed `SLOT_SIZE`: the
+    /// scratch buffer was only ever allocated that large. Callers that pack a fixed-size payload
+    /// (e.g. a quantized macroblock) into each packet should check it still fits the new limit
+    /// before relying on this — `send_bytes` only enforces the budget, not what made sense for it.
+    pub fn set_payload_size_limit(&mut self, limit: usize) {
+        assert!(
+            limit <= SLOT_SIZE,
+            "requested payload size limit {limit} exceeds the {SLOT_SIZE}-byte scratch buffer"
+        );
+        self.payload_size_limit = limit;
+    }
+
+    /// Enables (`group_size > 0`) or disables (`group_size == 0`) forward error correction: for
+    /// every `group_size` data packets sent, an extra parity packet goes out carrying the
+    /// byte-wise XOR of those payloads (zero-padded to the longest one), letting a receiver that's
+    /// missing exactly one packet of the group reconstruct it from the rest — see
+    /// [`RtpCircularBuffer::try_reconstruct_fec_group`]. Trades `1/group_size` extra bandwidth for
+    /// single-loss recovery without a retransmission round trip. Changing `group_size` (including
+    /// disabling it) discards any group currently being accumulated, the same as if its first
+    /// packets were lost — a receiver just won't be able to reconstruct from that partial group.
+    ///
+    /// Callers that need the two ends to agree on `group_size` (reconstruction only works if they
+    /// do) should negotiate it the same way as [`crate::MtuReport`] — e.g. piggybacked on
+    /// [`crate::ControlMessage::fec_group_size`].
+    pub fn set_fec_group_size(&mut self, group_size: u8) {
+        self.fec_group_size = group_size;
+        self.fec_group_in_progress = None;
+    }
+
     /// Send a packet over the network by filling data in the mutable slice.
     /// The closure `fill` is called with a mutable slice of the packet data, and should return the number of bytes to be sent.
-    pub fn send_bytes<'a>(&'a mut self, fill: impl FnOnce(&mut [u8]) -> usize) {
+    ///
+    /// `timestamp` and `marker` are stamped into the packet's [`PacketHeader`] as-is — this
+    /// sender auto-increments nothing but the sequence number, so a caller that wants jitter or
+    /// clock-drift estimation to mean anything on the receive side should pass a timestamp in
+    /// milliseconds on some shared epoch (e.g. [`crate::rtp_epoch`]).
+    ///
+    /// The packet is tagged as both the start and end of its own single-packet frame (see
+    /// [`Self::send_fragmented`] for payloads that don't fit in one packet).
+    pub fn send_bytes<'a>(&'a mut self, timestamp: u32, marker: bool, fill: impl FnOnce(&mut [u8]) -> usize) {
+        self.send_bytes_fragment(timestamp, marker, true, true, fill);
+    }
+
+    /// Sends `data` across as many packets as needed, fragmenting it if it's larger than
+    /// [`Self::payload_size_limit`] — mirrors an RTP aggregation-header payloader splitting a
+    /// too-large access unit. Each fragment consumes its own sequence number and is tagged with
+    /// [`PacketHeader::fragment_start`]/[`PacketHeader::fragment_end`], so
+    /// [`RtpCircularBuffer::reassemble_frame`] can tell a multi-packet frame's true boundary from
+    /// a gap. `data` that fits in one packet is sent exactly like [`Self::send_bytes`].
+    pub fn send_fragmented(&mut self, timestamp: u32, marker: bool, data: &[u8]) {
+        let limit = self.payload_size_limit;
+        let num_fragments = data.len().div_ceil(limit).max(1);
+
+        for i in 0..num_fragments {
+            let chunk = &data[i * limit..((i + 1) * limit).min(data.len())];
+            self.send_bytes_fragment(timestamp, marker, i == 0, i == num_fragments - 1, |mem| {
+                mem[..chunk.len()].copy_from_slice(chunk);
+                chunk.len()
+            });
+        }
+    }
+
+    fn send_bytes_fragment<'a>(
+        &'a mut self,
+        timestamp: u32,
+        marker: bool,
+        fragment_start: bool,
+        fragment_end: bool,
+        fill: impl FnOnce(&mut [u8]) -> usize,
+    ) {
         // Note that the size of the packets we use is less than 10kb, for which
         // https://www.kernel.org/doc/html/v6.3/networking/msg_zerocopy.html
         // copying is actually faster than MSG_ZEROCOPY.
 
+        let payload_size_limit = self.payload_size_limit;
+        let fec_group_id = self
+            .fec_group_in_progress
+            .as_ref()
+            .map_or(self.seq_num, |group| group.base_seq);
         let packet = &mut self.scratch;
 
         let header =
             PacketHeader::mut_from_bytes(&mut packet[0..size_of::<PacketHeader>()]).unwrap();
 
         header.sequence_number = self.seq_num.into();
-        
+        header.timestamp = timestamp.into();
+        header.ssrc = self.ssrc.into();
+        header.payload_type = self.payload_type;
+        header.set_marker(marker);
+        header.set_fragment_start(fragment_start);
+        header.set_fragment_end(fragment_end);
+        header.set_fec_group(fec_group_id, self.fec_group_size, false);
+        header.checksum = 0.into();
+
         // Note that this is only correct because the alignment of the packet is the same as the alignment of the payload.
         // Also #[repr(C)] on Packet should guarantee some amount of stability wrt. padding.
-        
+
         let packet_start_offset = offset_of!(Packet<AlignPayloadTo>, data);
-        let mem = &mut packet[packet_start_offset..];
+        let mem = &mut packet[packet_start_offset..packet_start_offset + payload_size_limit];
         let payload_len = fill(mem);
-        
-        super::udp_send(&self.sock, &packet[..packet_start_offset + payload_len]);
+
+        let packet_len = packet_start_offset + payload_len;
+        let checksum = compute_checksum(&packet[..packet_len]);
+        PacketHeader::mut_from_bytes(&mut packet[0..size_of::<PacketHeader>()])
+            .unwrap()
+            .checksum = checksum.into();
+
+        if let Err(e) = self.transport.send(&packet[..packet_len]) {
+            log::error!("failed to send seq {}: {e}", self.seq_num);
+        }
         log::trace!("sent seq: {} ({} bytes)", self.seq_num, packet_start_offset + payload_len);
-        
+
+        if self.fec_group_size > 0 {
+            let payload = &self.scratch[packet_start_offset..packet_start_offset + payload_len];
+            let group = self.fec_group_in_progress.get_or_insert_with(|| FecGroupInProgress {
+                base_seq: fec_group_id,
+                sent: 0,
+                parity: Vec::new(),
+            });
+            if group.parity.len() < payload.len() {
+                group.parity.resize(payload.len(), 0);
+            }
+            for (parity_byte, payload_byte) in group.parity.iter_mut().zip(payload) {
+                *parity_byte ^= payload_byte;
+            }
+            group.sent += 1;
+
+            if group.sent >= self.fec_group_size {
+                let group = self.fec_group_in_progress.take().unwrap();
+                self.seq_num = self.seq_num.wrapping_add(1);
+                self.send_fec_parity(timestamp, group.base_seq, &group.parity);
+                return;
+            }
+        }
+
+        self.seq_num = self.seq_num.wrapping_add(1);
+    }
+
+    /// Sends this FEC group's accumulated parity packet, consuming the next sequence number the
+    /// same way a data packet would. Called by [`Self::send_bytes_fragment`] once
+    /// [`Self::fec_group_size`] data packets have been folded into `parity`.
+    fn send_fec_parity(&mut self, timestamp: u32, group_id: u32, parity: &[u8]) {
+        let group_size = self.fec_group_size;
+        let packet = &mut self.scratch;
+
+        let header =
+            PacketHeader::mut_from_bytes(&mut packet[0..size_of::<PacketHeader>()]).unwrap();
+        header.sequence_number = self.seq_num.into();
+        header.timestamp = timestamp.into();
+        header.ssrc = self.ssrc.into();
+        header.payload_type = self.payload_type;
+        header.set_marker(false);
+        header.set_fragment_start(true);
+        header.set_fragment_end(true);
+        header.set_fec_group(group_id, group_size, true);
+        header.checksum = 0.into();
+
+        let packet_start_offset = offset_of!(Packet<AlignPayloadTo>, data);
+        packet[packet_start_offset..packet_start_offset + parity.len()].copy_from_slice(parity);
+
+        let packet_len = packet_start_offset + parity.len();
+        let checksum = compute_checksum(&packet[..packet_len]);
+        PacketHeader::mut_from_bytes(&mut packet[0..size_of::<PacketHeader>()])
+            .unwrap()
+            .checksum = checksum.into();
+
+        if let Err(e) = self.transport.send(&packet[..packet_len]) {
+            log::error!("failed to send FEC parity for group {group_id}: {e}");
+        }
+        log::trace!("sent FEC parity seq: {} (group {group_id}, {group_size} packets)", self.seq_num);
+
         self.seq_num = self.seq_num.wrapping_add(1);
     }
 }
@@ -384,14 +1447,15 @@ impl<
         Payload: FromBytes + TryFromBytes + IntoBytes + Immutable + KnownLayout,
         AlignPayloadTo: TryFromBytes + IntoBytes + KnownLayout + Immutable,
         const SLOT_SIZE: usize,
-    > RtpSender<Payload, AlignPayloadTo, SLOT_SIZE>
+        Tr: Transport,
+    > RtpSender<Payload, AlignPayloadTo, SLOT_SIZE, Tr>
 where
     [(); size_of_packet::<[u8; SLOT_SIZE]>()]: Sized,
 {
-    /// Send a packet over the network by filling data in the mutable slice.
-    /// The closure `fill` is called with a mutable reference of the data.
-    pub fn send<'a>(&'a mut self, fill: impl FnOnce(&mut Payload)) {
-        self.send_bytes(|mem| {
+    /// Send a packet over the network by filling data in the mutable reference.
+    /// See [`Self::send_bytes`] for what `timestamp` and `marker` mean.
+    pub fn send<'a>(&'a mut self, timestamp: u32, marker: bool, fill: impl FnOnce(&mut Payload)) {
+        self.send_bytes(timestamp, marker, |mem| {
             let mut data = Payload::mut_from_bytes(mem).unwrap();
             fill(&mut data);
             size_of_val(&data)
@@ -436,13 +1500,26 @@ impl<
 where
     [(); size_of_packet::<[u8; SLOT_SIZE]>()]: Sized,
 {
-    /// Launches listener thread that recieves packets and stores them in a buffer.
-    pub fn new(sock: UdpSocket) -> Self {
-        let rtp_circular_buffer = Arc::new(Mutex::new(RtpCircularBuffer::new()));
+    /// Launches listener thread that recieves packets over plain UDP and stores them in a buffer.
+    /// See [`Self::with_transport`] to receive over something other than plain UDP.
+    ///
+    /// `expected_ssrc` is the [`PacketHeader::ssrc`] this receiver accepts; packets stamped with
+    /// any other SSRC are assumed to belong to a different stream sharing the same socket/port
+    /// (e.g. the other of audio/video) and are dropped by [`accept_thread`] before they ever reach
+    /// the circular buffer.
+    pub fn new(sock: UdpSocket, expected_ssrc: u32) -> Self {
+        Self::with_transport(UdpTransport::new(sock), expected_ssrc)
+    }
+
+    /// Launches listener thread that receives packets over an arbitrary [`Transport`] and stores
+    /// them in a buffer. Use this instead of [`Self::new`] to receive over, e.g., an
+    /// [`crate::transport::EncryptedTransport`].
+    pub fn with_transport<Tr: Transport + 'static>(transport: Tr, expected_ssrc: u32) -> Self {
+        let rtp_circular_buffer = Arc::new(Mutex::new(RtpCircularBuffer::new(expected_ssrc)));
 
         let cloned_rtp_circular_buffer = rtp_circular_buffer.clone();
         std::thread::spawn(move || {
-            accept_thread(sock, cloned_rtp_circular_buffer);
+            accept_thread(transport, cloned_rtp_circular_buffer);
         });
 
         RtpReceiver {
@@ -456,6 +1533,50 @@ where
     ) -> MutexGuard<'_, RtpCircularBuffer<Payload, AlignPayloadTo, SLOT_SIZE, BUFFER_LENGTH>> {
         self.rtp_circular_buffer.lock().unwrap()
     }
+
+    /// Computes a [`ReceiverReport`] summarizing loss and jitter since the last call.
+    /// Intended to be sent back to the corresponding sender periodically (e.g. piggybacked
+    /// on the existing control-message channel) so it can scale its send budget under loss.
+    pub fn receiver_report(&self) -> ReceiverReport {
+        self.lock_receiver().take_receiver_report()
+    }
+
+    /// The smoothed offset between the sender's timestamp clock and this receiver's own clock, in
+    /// milliseconds. `None` until the first packet has arrived. See [`ClockDriftSmoother`].
+    pub fn clock_drift_offset_millis(&self) -> Option<f64> {
+        self.lock_receiver().clock_drift_offset_millis()
+    }
+
+    /// Adaptive jitter-buffer depth target, in packets. See [`RtpCircularBuffer::target_buffer_span`].
+    pub fn target_buffer_span(&self, packet_interval_millis: f64) -> u32 {
+        self.lock_receiver().target_buffer_span(packet_interval_millis)
+    }
+
+    /// Tunes the adaptive jitter buffer's latency-vs-smoothness tradeoff. See
+    /// [`RtpCircularBuffer::set_jitter_buffer_params`].
+    pub fn set_jitter_buffer_params(&self, min: u32, max: u32, scale: f64) {
+        self.lock_receiver().set_jitter_buffer_params(min, max, scale)
+    }
+
+    /// Occupancy/capacity snapshot of the reorder window. See [`BufferLimits`].
+    pub fn limits(&self) -> BufferLimits {
+        self.lock_receiver().limits()
+    }
+
+    /// Diagnoses why the earliest slot isn't ready. See [`RtpCircularBuffer::diagnose_gap`].
+    pub fn diagnose_gap(&self) -> PlayoutGap {
+        self.lock_receiver().diagnose_gap()
+    }
+
+    /// Skips a slot diagnosed as [`PlayoutGap::Lost`]. See [`RtpCircularBuffer::skip_lost_packet`].
+    pub fn skip_lost_packet(&self) {
+        self.lock_receiver().skip_lost_packet()
+    }
+
+    /// Resizes the reorder window. See [`RtpCircularBuffer::set_target_capacity`].
+    pub fn set_target_capacity(&self, new_capacity: usize) {
+        self.lock_receiver().set_target_capacity(new_capacity)
+    }
 }
 
 fn accept_thread<
@@ -463,24 +1584,42 @@ fn accept_thread<
     AlignPayloadTo: TryFromBytes + IntoBytes + KnownLayout + Immutable,
     const SLOT_SIZE: usize,
     const BUFFER_LENGTH: usize,
+    Tr: Transport,
 >(
-    sock: UdpSocket,
+    transport: Tr,
     recv: Arc<Mutex<RtpCircularBuffer<Payload, AlignPayloadTo, SLOT_SIZE, BUFFER_LENGTH>>>,
 ) where
     [(); size_of_packet::<[u8; SLOT_SIZE]>()]: Sized,
 {
-    sock.set_nonblocking(false).unwrap();
-    log::info!("Receiver started listening on {:?}.", sock.local_addr());
+    log::info!("Receiver thread started.");
 
     loop {
-        // wait until socket has a packet to read
-        let mut seq_num_buffer = [0u8; 4];
-        sock.peek(&mut seq_num_buffer).unwrap();
+        // wait until a datagram is available to read; peek far enough in to also see the SSRC.
+        let ssrc_offset = offset_of!(PacketHeader, ssrc);
+        let mut header_prefix_buffer = [0u8; size_of::<PacketHeader>()];
+        transport.peek(&mut header_prefix_buffer).unwrap();
 
         // we have available data to read
         let mut state = recv.lock().unwrap();
 
-        let seq_num: u32 = U32::from_bytes(seq_num_buffer).into();
+        let seq_num: u32 = U32::from_bytes(header_prefix_buffer[0..4].try_into().unwrap()).into();
+        let ssrc: u32 = U32::from_bytes(
+            header_prefix_buffer[ssrc_offset..ssrc_offset + 4]
+                .try_into()
+                .unwrap(),
+        )
+        .into();
+
+        if ssrc != state.expected_ssrc {
+            // Packet belongs to a different stream sharing this socket/port; discard it without
+            // disturbing our sequence-number bookkeeping.
+            let _ = transport.recv(&mut header_prefix_buffer);
+            log::debug!(
+                "dropping packet with ssrc {ssrc:#x}; expected {:#x}",
+                state.expected_ssrc
+            );
+            continue;
+        }
 
         // If the received packet has a place in the buffer, write the packet to the correct slot.
         // The received packet is allowed a place if its sequence number is larger than the earliest packet
@@ -488,33 +1627,58 @@ fn accept_thread<
 
         if (seq_num.wrapping_sub(state.earliest_seq)) < u32::MAX / 2 {
             // If this packet will need to overwrite old existing packets.
-            if seq_num.wrapping_sub(state.earliest_seq) as usize >= state.buf.len() {
+            if seq_num.wrapping_sub(state.earliest_seq) as usize >= state.meta.len() {
                 log::debug!(
                     "received an advanced packet with seq {}; dropping packets from {} to {}",
                     seq_num,
                     state.earliest_seq,
-                    seq_num.wrapping_sub(state.buf.len() as u32)
+                    seq_num.wrapping_sub(state.meta.len() as u32)
                 );
-                while seq_num.wrapping_sub(state.earliest_seq) as usize >= state.buf.len() {
+                while seq_num.wrapping_sub(state.earliest_seq) as usize >= state.meta.len() {
                     // Drop old packets until we can fit this new one.
-                    state.consume_earliest_packet();
+                    state.consume_earliest_packet_unchecked();
                 }
             }
 
+            if state.get(seq_num).is_some_and(|p| p.is_init()) {
+                // Already have a packet in this slot: a duplicate/retransmitted datagram, not a
+                // conflicting new arrival (the window-advance above would have cleared the slot
+                // first if this were a legitimately new sequence number). Drop it rather than
+                // clobbering the original.
+                let _ = transport.recv(&mut header_prefix_buffer);
+                log::debug!("dropping duplicate seq_num {seq_num}");
+                continue;
+            }
+
             state.early_latest_span = u32::max(
                 state.early_latest_span,
                 seq_num.wrapping_sub(state.earliest_seq),
             );
-            let MaybeInitPacket {
-                recv_size: init,
-                packet,
-                ..
-            } = state
-                .get_mut(seq_num)
-                .expect("Circular buffer should have space for packet.");
 
-            let len = sock.recv(packet).unwrap();
-            *init = Some(NonZero::new(len).expect("Packet should have non-zero length."));
+            let mut recv_scratch = [0u8; size_of_packet::<[u8; SLOT_SIZE]>()];
+            let len = transport.recv(&mut recv_scratch).unwrap();
+            let packet = &recv_scratch[..len];
+
+            let checksum_offset = offset_of!(PacketHeader, checksum);
+            let received_checksum =
+                U16::from_bytes(packet[checksum_offset..checksum_offset + 2].try_into().unwrap());
+            let computed_checksum = compute_checksum(packet);
+            if u16::from(received_checksum) != computed_checksum {
+                log::debug!(
+                    "dropping seq_num {seq_num} for failing checksum verification (got {:#x}, expected {:#x})",
+                    u16::from(received_checksum),
+                    computed_checksum
+                );
+                continue;
+            }
+
+            let timestamp_offset = offset_of!(PacketHeader, timestamp);
+            let packet_timestamp: u32 = U32::from_bytes(
+                packet[timestamp_offset..timestamp_offset + 4]
+                    .try_into()
+                    .unwrap(),
+            )
+            .into();
 
             if len > 16 {
                 log::trace!(
@@ -525,15 +1689,35 @@ fn accept_thread<
             } else {
                 log::trace!("received seq_num {seq_num} and raw data: {:?}", &packet);
             }
+
+            let fec_group_id_offset = offset_of!(PacketHeader, fec_group_id);
+            let fec_group_id: u32 = U32::from_bytes(
+                packet[fec_group_id_offset..fec_group_id_offset + 4]
+                    .try_into()
+                    .unwrap(),
+            )
+            .into();
+            let fec_group_size = packet[offset_of!(PacketHeader, fec_group_size)];
+
+            if !state.store_packet(seq_num, packet) {
+                continue;
+            }
+
+            state.report_stats.on_packet_received(seq_num, packet_timestamp);
+            state.clock_drift.on_packet_received(packet_timestamp);
+
+            if fec_group_size > 0 {
+                state.try_reconstruct_fec_group(fec_group_id, fec_group_size);
+            }
         } else {
             // Otherwise, discard the packet.
 
-            let _ = sock.recv(&mut seq_num_buffer);
+            let _ = transport.recv(&mut header_prefix_buffer);
             log::debug!(
                 "dropping seq_num {} for being too early/late; accepted range is {}-{}",
                 seq_num,
                 state.earliest_seq,
-                state.earliest_seq + state.buf.len() as u32
+                state.earliest_seq + state.meta.len() as u32
             );
             continue;
         }
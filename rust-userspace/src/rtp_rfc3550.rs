@@ -0,0 +1,133 @@
+//! A standards-compliant alternative to [`crate::rtp::PacketHeader`]'s bespoke framing, so a
+//! keymashed stream can be read by ordinary RTP tooling (Wireshark's RTP dissector, `ffplay
+//! -protocol_whitelist ...`, a real SIP/WebRTC stack) instead of only another copy of this crate.
+//! [`Rfc3550Header`] is the real fixed 12-byte RTP header (RFC 3550 5.1); [`send_aac_access_unit`]
+//! packetizes an MPEG-4 AAC access unit the way RFC 3016's MP4A-LATM payloader does.
+//!
+//! This intentionally doesn't carry any of [`crate::rtp::PacketHeader`]'s extras (checksum, FEC
+//! group, fragment-start/end bits): a standard header only has room for what RFC 3550 defines, and
+//! bolting non-standard fields onto it would defeat the point of being readable by tools that don't
+//! know about this crate. [`crate::rtp::RtpSender`]/[`crate::rtp::RtpReceiver`] keep using the
+//! compact format; a stream picks whichever of the two it wants at the call site (see
+//! [`crate::audio::AudioHeaderFormat`]), so both coexist without needing to share plumbing.
+
+use std::io;
+use std::net::UdpSocket;
+
+use zerocopy::byteorder::network_endian::{U16, U32};
+use zerocopy::{FromBytes, Immutable, IntoBytes, KnownLayout, Unaligned};
+
+/// RTP version this module emits/expects (RFC 3550 5.1) — the only version that has ever existed.
+const RTP_VERSION: u8 = 2;
+
+/// The fixed 12-byte RTP header (RFC 3550 5.1), with no CSRC list (`cc` always `0`) and no header
+/// extension (`x` always `0`) — keymashed never needs either, and omitting them keeps every
+/// packet's header the same fixed [`Self::BYTES`] size instead of variable-length.
+#[derive(Debug, Clone, Copy, FromBytes, IntoBytes, KnownLayout, Immutable, Unaligned)]
+#[repr(C)]
+pub struct Rfc3550Header {
+    /// `version(2) | padding(1) | extension(1) | csrc_count(4)`; always `0b10_0_0_0000` here.
+    version_flags: u8,
+    /// `marker(1) | payload_type(7)`.
+    marker_payload_type: u8,
+    sequence_number: U16,
+    timestamp: U32,
+    ssrc: U32,
+}
+
+impl Rfc3550Header {
+    /// Wire size of this header — always 12 bytes, since `cc`/`x` are always `0` here.
+    pub const BYTES: usize = size_of::<Self>();
+
+    pub fn new(sequence_number: u16, timestamp: u32, ssrc: u32, payload_type: u8, marker: bool) -> Self {
+        assert!(
+            payload_type < 0x80,
+            "RTP payload type {payload_type} doesn't fit in the header's 7-bit field"
+        );
+        Rfc3550Header {
+            version_flags: RTP_VERSION << 6,
+            marker_payload_type: ((marker as u8) << 7) | payload_type,
+            sequence_number: sequence_number.into(),
+            timestamp: timestamp.into(),
+            ssrc: ssrc.into(),
+        }
+    }
+
+    pub fn version(&self) -> u8 {
+        self.version_flags >> 6
+    }
+
+    pub fn marker(&self) -> bool {
+        self.marker_payload_type & 0x80 != 0
+    }
+
+    pub fn payload_type(&self) -> u8 {
+        self.marker_payload_type & 0x7f
+    }
+
+    pub fn sequence_number(&self) -> u16 {
+        self.sequence_number.into()
+    }
+
+    pub fn timestamp(&self) -> u32 {
+        self.timestamp.into()
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        self.ssrc.into()
+    }
+}
+
+/// Sends one MPEG-4 AAC access unit (see [`crate::audio_codec::encode`]'s output, standing in for
+/// a real AAC bitstream the same way that module already stands in for a real perceptual codec) as
+/// one or more RTP packets carrying an [`Rfc3550Header`], mirroring RFC 3016's MP4A-LATM
+/// payloader: the access unit is split across as many `mtu`-sized packets as it takes, every
+/// fragment shares the access unit's RTP timestamp, and only the last fragment has the marker bit
+/// set. A depayloader reassembles by concatenating payloads sharing one timestamp until it sees
+/// the marker bit, the same role [`crate::rtp::PacketHeader::fragment_end`] plays for the compact
+/// format.
+///
+/// `sequence_number` is advanced past however many fragments this call sends, so the caller can
+/// thread it straight into the next access unit's call. `timestamp` is the caller's
+/// responsibility to advance too — by the access unit's sample count (RFC 3550 5.1's "sampling
+/// instant", e.g. [`crate::audio::AUDIO_SAMPLE_COUNT`] for this crate's audio frames), not by 1
+/// per packet the way [`crate::rtp::RtpSender`]'s caller-supplied timestamp is used.
+pub fn send_aac_access_unit(
+    sock: &UdpSocket,
+    ssrc: u32,
+    payload_type: u8,
+    sequence_number: &mut u16,
+    timestamp: u32,
+    mtu: usize,
+    access_unit: &[u8],
+) -> io::Result<()> {
+    let mut packet = vec![0u8; Rfc3550Header::BYTES + mtu];
+    let num_fragments = access_unit.len().div_ceil(mtu).max(1);
+
+    for i in 0..num_fragments {
+        let chunk = &access_unit[i * mtu..((i + 1) * mtu).min(access_unit.len())];
+        let header = Rfc3550Header::new(
+            *sequence_number,
+            timestamp,
+            ssrc,
+            payload_type,
+            i == num_fragments - 1,
+        );
+        packet[..Rfc3550Header::BYTES].copy_from_slice(header.as_bytes());
+        packet[Rfc3550Header::BYTES..Rfc3550Header::BYTES + chunk.len()].copy_from_slice(chunk);
+        sock.send(&packet[..Rfc3550Header::BYTES + chunk.len()])?;
+        *sequence_number = sequence_number.wrapping_add(1);
+    }
+    Ok(())
+}
+
+/// Reads one RTP packet out of `buf` (as received off the wire), returning its header and the
+/// payload slice past it — the inverse of [`send_aac_access_unit`]'s per-packet framing. Returns
+/// `None` if `buf` is too short to even hold a header.
+pub fn parse_packet(buf: &[u8]) -> Option<(Rfc3550Header, &[u8])> {
+    if buf.len() < Rfc3550Header::BYTES {
+        return None;
+    }
+    let header = Rfc3550Header::read_from_bytes(&buf[..Rfc3550Header::BYTES]).unwrap();
+    Some((header, &buf[Rfc3550Header::BYTES..]))
+}
@@ -0,0 +1,251 @@
+//! Receive-side stream-health statistics, loosely inspired by Chromium Cast's stats-event
+//! subscriber: accumulate cheap counters and bucketed histograms as frames come in, rather than
+//! keeping every raw sample around, and expose rolling rates (fps, loss %) computed over a
+//! recent time window.
+//!
+//! This binary's receiver renders straight into an SDL2 window rather than through a terminal
+//! TUI, so there's no `ratatui` `Chart`/`Dataset` for [`ReceiverStats`] to feed directly; see
+//! [`ReceiverStats::summary`] for the periodic log-line a caller can use instead to make the
+//! numbers observable.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+/// How far back [`RollingWindow`] looks when computing a rate — long enough to smooth over a
+/// single bad frame, short enough that the reported rate still reflects "right now".
+const ROLLING_WINDOW: Duration = Duration::from_secs(5);
+
+/// A fixed-width linear histogram over `[0, bucket_width * buckets.len())`, with an overflow
+/// bucket catching anything at or past that ceiling. Used by [`ReceiverStats`] to bucket one-shot
+/// per-frame measurements (latency, deadline overshoot, dropped-macroblock count) cheaply, since
+/// keeping every raw sample for the stream's lifetime isn't worth it.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    bucket_width: f64,
+    buckets: Vec<u64>,
+    overflow: u64,
+    count: u64,
+    sum: f64,
+}
+
+impl Histogram {
+    pub fn new(bucket_width: f64, bucket_count: usize) -> Self {
+        assert!(bucket_width > 0.0, "histogram bucket width must be positive");
+        Histogram {
+            bucket_width,
+            buckets: vec![0; bucket_count],
+            overflow: 0,
+            count: 0,
+            sum: 0.0,
+        }
+    }
+
+    /// Buckets `value`, clamping negative values into bucket 0 rather than panicking.
+    pub fn record(&mut self, value: f64) {
+        self.count += 1;
+        self.sum += value;
+
+        let bucket = (value.max(0.0) / self.bucket_width) as usize;
+        match self.buckets.get_mut(bucket) {
+            Some(slot) => *slot += 1,
+            None => self.overflow += 1,
+        }
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Mean of every recorded value (not bucket midpoints), so it stays exact regardless of
+    /// `bucket_width`.
+    pub fn mean(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.sum / self.count as f64
+        }
+    }
+
+    /// Per-bucket counts, in order; index `i` covers `[i * bucket_width, (i + 1) * bucket_width)`.
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets
+    }
+
+    /// Count of values at or past the last bucket's ceiling.
+    pub fn overflow(&self) -> u64 {
+        self.overflow
+    }
+}
+
+/// A sliding time window of timestamped samples, used to turn a stream of one-shot events into a
+/// rolling rate — the same smoothing [`crate::wpm::TypingMetrics`] does for keystrokes, applied
+/// here to frame presentation/skip events instead.
+#[derive(Debug)]
+struct RollingWindow {
+    window: Duration,
+    samples: VecDeque<(Instant, f64)>,
+}
+
+impl RollingWindow {
+    fn new(window: Duration) -> Self {
+        RollingWindow {
+            window,
+            samples: VecDeque::new(),
+        }
+    }
+
+    fn push(&mut self, value: f64) {
+        let now = Instant::now();
+        self.prune(now);
+        self.samples.push_back((now, value));
+    }
+
+    fn prune(&mut self, now: Instant) {
+        while self
+            .samples
+            .front()
+            .is_some_and(|(t, _)| now.duration_since(*t) > self.window)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    /// Sum of every value still in the window, divided by how much of `self.window` has actually
+    /// elapsed (rather than the nominal window length), so the rate isn't under-reported right
+    /// after startup before the window has filled up.
+    fn rate_per_sec(&mut self) -> f64 {
+        self.prune(Instant::now());
+        let Some((oldest, _)) = self.samples.front() else {
+            return 0.0;
+        };
+        let elapsed = oldest.elapsed().as_secs_f64().max(1e-3);
+        let total: f64 = self.samples.iter().map(|(_, v)| v).sum();
+        total / elapsed
+    }
+
+    /// Mean of every value still in the window, `0.0` if it's empty.
+    fn mean(&mut self) -> f64 {
+        self.prune(Instant::now());
+        if self.samples.is_empty() {
+            0.0
+        } else {
+            self.samples.iter().map(|(_, v)| v).sum::<f64>() / self.samples.len() as f64
+        }
+    }
+}
+
+/// Live accumulated statistics for the video receive path: frames presented vs. skipped,
+/// per-frame macroblock loss, per-frame latency as the caller measures it, and how far presentation overshot its
+/// deadline. Replaces the scattered `log::info!("Playing frame ...")`-style lines with a single
+/// place a caller can both query (for a dashboard) and periodically log (see [`Self::summary`]).
+#[derive(Debug)]
+pub struct ReceiverStats {
+    frames_presented: u64,
+    frames_skipped: u64,
+    frame_latency_ms: Histogram,
+    deadline_overshoot_ms: Histogram,
+    macroblocks_dropped: Histogram,
+    presented_window: RollingWindow,
+    /// Per-frame `dropped / total` macroblock ratios, smoothed into a rolling loss percentage —
+    /// the stat the WPM→drop-rate→video-degradation feedback loop is really about, as opposed to
+    /// [`Self::frames_skipped`] (whole frames never presented at all).
+    loss_window: RollingWindow,
+}
+
+impl ReceiverStats {
+    pub fn new() -> Self {
+        ReceiverStats {
+            frames_presented: 0,
+            frames_skipped: 0,
+            // 5ms-wide buckets up to 200ms, which comfortably covers a 30fps frame interval.
+            frame_latency_ms: Histogram::new(5.0, 40),
+            // 2ms-wide buckets up to 100ms of overshoot.
+            deadline_overshoot_ms: Histogram::new(2.0, 50),
+            // 5-macroblock-wide buckets, up to 200 macroblocks missing.
+            macroblocks_dropped: Histogram::new(5.0, 40),
+            presented_window: RollingWindow::new(ROLLING_WINDOW),
+            loss_window: RollingWindow::new(ROLLING_WINDOW),
+        }
+    }
+
+    /// Records a frame that was fully decoded and presented, taking its `latency` and, if
+    /// presenting it ran past the frame's deadline, by how much. `latency` is whatever span the
+    /// caller measured: the `recv` binary passes the time its own loop spent receiving, decoding
+    /// and drawing the frame, not capture-to-display time.
+    pub fn record_frame_presented(&mut self, latency: Duration, deadline_overshoot: Option<Duration>) {
+        self.frames_presented += 1;
+        self.frame_latency_ms.record(latency.as_secs_f64() * 1000.0);
+        self.presented_window.push(1.0);
+        if let Some(overshoot) = deadline_overshoot {
+            self.deadline_overshoot_ms.record(overshoot.as_secs_f64() * 1000.0);
+        }
+    }
+
+    /// Records that the receiver fell behind and skipped ahead to a newer frame instead of
+    /// presenting the one it was decoding.
+    pub fn record_frame_skipped(&mut self) {
+        self.frames_skipped += 1;
+    }
+
+    /// Records how many of a frame's macroblocks never arrived in time to be drawn, out of
+    /// `total` in the frame.
+    pub fn record_macroblocks_dropped(&mut self, dropped: usize, total: usize) {
+        self.macroblocks_dropped.record(dropped as f64);
+        if total > 0 {
+            self.loss_window.push(dropped as f64 / total as f64);
+        }
+    }
+
+    /// Frames presented per second, smoothed over the last [`ROLLING_WINDOW`].
+    pub fn fps(&mut self) -> f64 {
+        self.presented_window.rate_per_sec()
+    }
+
+    /// Rolling macroblock-loss percentage, in `[0.0, 100.0]`, averaged over the last
+    /// [`ROLLING_WINDOW`] — what a caller should plot as "packet loss %".
+    pub fn loss_percent(&mut self) -> f64 {
+        self.loss_window.mean() * 100.0
+    }
+
+    pub fn frames_presented(&self) -> u64 {
+        self.frames_presented
+    }
+
+    pub fn frames_skipped(&self) -> u64 {
+        self.frames_skipped
+    }
+
+    pub fn frame_latency_histogram(&self) -> &Histogram {
+        &self.frame_latency_ms
+    }
+
+    pub fn deadline_overshoot_histogram(&self) -> &Histogram {
+        &self.deadline_overshoot_ms
+    }
+
+    pub fn macroblocks_dropped_histogram(&self) -> &Histogram {
+        &self.macroblocks_dropped
+    }
+
+    /// A one-line human-readable summary, meant for a periodic `log::info!` call so stream health
+    /// stays observable without a dashboard to render it into.
+    pub fn summary(&mut self) -> String {
+        format!(
+            "fps {:.1}, loss {:.1}%, frames skipped {}, avg dropped macroblocks/frame {:.1}, avg latency {:.1}ms, avg overshoot {:.1}ms",
+            self.fps(),
+            self.loss_percent(),
+            self.frames_skipped,
+            self.macroblocks_dropped.mean(),
+            self.frame_latency_ms.mean(),
+            self.deadline_overshoot_ms.mean(),
+        )
+    }
+}
+
+impl Default for ReceiverStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,290 @@
+//! Pluggable datagram transports for [`crate::rtp::RtpSender`]/[`crate::rtp::RtpReceiver`], so the
+//! packet-framing/reorder logic never has to know whether it's riding over a raw UDP socket or
+//! something else underneath — mirroring the reader/writer-plus-optional-encryption layering
+//! lonelyradio uses for its own transport.
+
+use std::{
+    io,
+    net::UdpSocket,
+    sync::Mutex,
+};
+
+use crate::{aead, chacha20};
+
+/// One packet in, one packet out, matching `UdpSocket`'s own datagram semantics so
+/// [`UdpTransport`] can wrap one directly. An implementation is free to transform the bytes
+/// underneath (e.g. [`EncryptedTransport`]) as long as a peer's matching `Transport` sees through
+/// it the same way it was wrapped.
+pub trait Transport: Send + Sync {
+    fn send(&self, buf: &[u8]) -> io::Result<()>;
+    /// Blocks until a datagram arrives, writing it into `buf` and returning its length — mirrors
+    /// `UdpSocket::recv`'s truncate-if-too-small behavior.
+    fn recv(&self, buf: &mut [u8]) -> io::Result<usize>;
+    /// Returns (a view of) the next datagram without consuming it — mirrors `UdpSocket::peek`.
+    /// Used by [`crate::rtp::accept_thread`] to read just enough of the header to decide whether
+    /// it even wants the packet before paying for the full `recv`.
+    fn peek(&self, buf: &mut [u8]) -> io::Result<usize>;
+}
+
+/// The original (and, until now, only) transport: a thin pass-through to a bound/connected
+/// `UdpSocket`, in cleartext.
+pub struct UdpTransport(UdpSocket);
+
+impl UdpTransport {
+    /// Wraps `sock`, putting it in blocking mode — [`crate::rtp::accept_thread`] relies on `recv`
+    /// blocking until a datagram is available rather than spinning.
+    pub fn new(sock: UdpSocket) -> Self {
+        sock.set_nonblocking(false).unwrap();
+        UdpTransport(sock)
+    }
+}
+
+impl Transport for UdpTransport {
+    fn send(&self, buf: &[u8]) -> io::Result<()> {
+        self.0.send(buf).map(|_| ())
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.recv(buf)
+    }
+
+    fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.peek(buf)
+    }
+}
+
+/// Byte width of [`crate::rtp::PacketHeader::sequence_number`] on the wire — every packet this
+/// crate ever frames starts with it (that struct is `#[repr(C)]` with `sequence_number` as its
+/// first field), so [`EncryptedTransport`] can locate it without depending on `rtp` directly.
+const SEQUENCE_NUMBER_BYTES: usize = 4;
+
+/// Upper bound on a single UDP datagram's size, used to size [`AuthenticatedEncryptedTransport::peek`]'s
+/// scratch buffer independent of the caller's own (possibly much smaller, e.g. header-only) `buf` —
+/// the IPv4/IPv6 max payload a socket can ever hand back from one `recv`/`peek` call.
+const MAX_DATAGRAM_BYTES: usize = 65_507;
+
+/// Wraps an inner [`Transport`] with ChaCha20 keystream encryption (RFC 8439), keyed from a
+/// pre-shared secret — the same hand-rolled-instead-of-vendored spirit as [`crate::audio_codec`]:
+/// enough to keep traffic off an untrusted network from being read in cleartext, not a vetted
+/// crypto implementation meant to resist a motivated attacker.
+///
+/// The first [`SEQUENCE_NUMBER_BYTES`] of every packet (the RTP sequence number) are left
+/// unencrypted and double as part of the keystream's nonce. Without that, a receiver would have no
+/// way to know which nonce to decrypt an out-of-order UDP datagram with before it's decrypted —
+/// trading away sequence-number confidentiality buys every packet being independently
+/// decryptable regardless of loss or reordering, the same tradeoff SRTP's cleartext sequence
+/// number makes for the same reason.
+pub struct EncryptedTransport<T: Transport> {
+    inner: T,
+    key: [u8; chacha20::KEY_BYTES],
+}
+
+impl<T: Transport> EncryptedTransport<T> {
+    pub fn new(inner: T, key: [u8; chacha20::KEY_BYTES]) -> Self {
+        EncryptedTransport { inner, key }
+    }
+
+    fn nonce_from_sequence_number(sequence_number_bytes: [u8; SEQUENCE_NUMBER_BYTES]) -> [u8; chacha20::NONCE_BYTES] {
+        let mut nonce = [0u8; chacha20::NONCE_BYTES];
+        nonce[..SEQUENCE_NUMBER_BYTES].copy_from_slice(&sequence_number_bytes);
+        nonce
+    }
+
+    /// Applies the keystream to everything in `buf` past the cleartext sequence number, deriving
+    /// the nonce from that same sequence number. Used identically by both `send` (to encrypt) and
+    /// `recv`/`peek` (to decrypt) — ChaCha20 is its own inverse.
+    fn apply_keystream_past_sequence_number(&self, buf: &mut [u8]) {
+        if buf.len() < SEQUENCE_NUMBER_BYTES {
+            // Too short to have a sequence number at all; nothing sensible to encrypt.
+            return;
+        }
+        let sequence_number_bytes: [u8; SEQUENCE_NUMBER_BYTES] =
+            buf[..SEQUENCE_NUMBER_BYTES].try_into().unwrap();
+        let nonce = Self::nonce_from_sequence_number(sequence_number_bytes);
+        chacha20::apply_keystream(&self.key, &nonce, 0, &mut buf[SEQUENCE_NUMBER_BYTES..]);
+    }
+}
+
+impl<T: Transport> Transport for EncryptedTransport<T> {
+    fn send(&self, buf: &[u8]) -> io::Result<()> {
+        let mut scratch = buf.to_vec();
+        self.apply_keystream_past_sequence_number(&mut scratch);
+        self.inner.send(&scratch)
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.recv(buf)?;
+        self.apply_keystream_past_sequence_number(&mut buf[..len]);
+        Ok(len)
+    }
+
+    fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        // `peek` doesn't consume the datagram, but accept_thread still reads real header fields
+        // (ssrc, in particular) out of what it returns, so it needs decrypting same as `recv`.
+        let len = self.inner.peek(buf)?;
+        self.apply_keystream_past_sequence_number(&mut buf[..len]);
+        Ok(len)
+    }
+}
+
+/// Random value unique to one stream's lifetime, folded into every packet's nonce alongside its
+/// (already-cleartext, monotonically increasing) sequence number — see
+/// [`AuthenticatedEncryptedTransport`].
+pub type StreamSalt = [u8; aead::NONCE_BYTES - 4];
+
+/// [`EncryptedTransport`], but authenticated: wraps an inner [`Transport`] with full ChaCha20-
+/// Poly1305 (RFC 8439) instead of bare ChaCha20 keystream, so a datagram that was tampered with or
+/// forged in transit is rejected outright rather than silently decrypted into garbage macroblocks.
+/// Like [`EncryptedTransport`], the leading [`SEQUENCE_NUMBER_BYTES`] stay cleartext and double as
+/// the nonce's packet counter; unlike it, that counter is combined with a per-stream [`StreamSalt`]
+/// so two sessions sharing a key never collide on a nonce.
+pub struct AuthenticatedEncryptedTransport<T: Transport> {
+    inner: T,
+    key: [u8; aead::KEY_BYTES],
+    salt: StreamSalt,
+    /// [`Transport::peek`]'s scratch buffer, preallocated once at [`MAX_DATAGRAM_BYTES`] and
+    /// reused on every call instead of heap-allocating fresh — `peek` runs in
+    /// [`crate::rtp::accept_thread`]'s hot per-incoming-datagram loop, where a 64KB `Vec` per
+    /// call would otherwise replace what used to be a small stack array. Behind a `Mutex` rather
+    /// than a `RefCell` since [`Transport`] requires `Sync`.
+    peek_scratch: Mutex<Vec<u8>>,
+}
+
+impl<T: Transport> AuthenticatedEncryptedTransport<T> {
+    pub fn new(inner: T, key: [u8; aead::KEY_BYTES], salt: StreamSalt) -> Self {
+        AuthenticatedEncryptedTransport {
+            inner,
+            key,
+            salt,
+            peek_scratch: Mutex::new(vec![0u8; MAX_DATAGRAM_BYTES]),
+        }
+    }
+
+    fn nonce_for(&self, sequence_number_bytes: [u8; SEQUENCE_NUMBER_BYTES]) -> [u8; aead::NONCE_BYTES] {
+        aead::nonce_from_salt_and_counter(self.salt, u32::from_le_bytes(sequence_number_bytes))
+    }
+
+    /// Verifies and decrypts one raw (sequence number || sealed ciphertext) datagram, returning
+    /// the plaintext packet (sequence number prefix still attached) or `None` if its tag didn't
+    /// verify.
+    fn open_packet(&self, raw: &[u8]) -> Option<Vec<u8>> {
+        if raw.len() < SEQUENCE_NUMBER_BYTES {
+            return None;
+        }
+        let sequence_number_bytes: [u8; SEQUENCE_NUMBER_BYTES] =
+            raw[..SEQUENCE_NUMBER_BYTES].try_into().unwrap();
+        let nonce = self.nonce_for(sequence_number_bytes);
+        let plaintext_body = aead::open(&self.key, &nonce, &raw[SEQUENCE_NUMBER_BYTES..])?;
+
+        let mut plaintext = Vec::with_capacity(SEQUENCE_NUMBER_BYTES + plaintext_body.len());
+        plaintext.extend_from_slice(&sequence_number_bytes);
+        plaintext.extend_from_slice(&plaintext_body);
+        Some(plaintext)
+    }
+}
+
+impl<T: Transport> Transport for AuthenticatedEncryptedTransport<T> {
+    fn send(&self, buf: &[u8]) -> io::Result<()> {
+        if buf.len() < SEQUENCE_NUMBER_BYTES {
+            // Too short to have a sequence number to derive a nonce from; nothing sensible to seal.
+            return self.inner.send(buf);
+        }
+        let sequence_number_bytes: [u8; SEQUENCE_NUMBER_BYTES] =
+            buf[..SEQUENCE_NUMBER_BYTES].try_into().unwrap();
+        let nonce = self.nonce_for(sequence_number_bytes);
+        let sealed = aead::seal(&self.key, &nonce, &buf[SEQUENCE_NUMBER_BYTES..]);
+
+        let mut packet = Vec::with_capacity(SEQUENCE_NUMBER_BYTES + sealed.len());
+        packet.extend_from_slice(&sequence_number_bytes);
+        packet.extend_from_slice(&sealed);
+        self.inner.send(&packet)
+    }
+
+    fn recv(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut scratch = vec![0u8; buf.len() + aead::TAG_BYTES];
+        loop {
+            let len = self.inner.recv(&mut scratch)?;
+            // A packet that fails authentication is dropped exactly like a lost one: keep
+            // blocking for the next datagram instead of ever handing the caller unverified bytes.
+            if let Some(plaintext) = self.open_packet(&scratch[..len]) {
+                buf[..plaintext.len()].copy_from_slice(&plaintext);
+                return Ok(plaintext.len());
+            }
+        }
+    }
+
+    fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+        // Unlike a cleartext header or `EncryptedTransport`'s bare keystream, a sealed AEAD
+        // packet can't be partially verified from a prefix — the Poly1305 tag covers (and
+        // trails) the *entire* ciphertext, so a scratch buffer sized off the caller's `buf`
+        // (as `recv` above does, fine there since its caller always passes a full-packet-sized
+        // buffer) would truncate real packets whenever `buf` is smaller, as `accept_thread`'s
+        // header-only peek is. Always peek a full-datagram-sized scratch here regardless of
+        // `buf.len()`, and only copy out the prefix the caller actually asked for.
+        let mut scratch = self.peek_scratch.lock().unwrap();
+        loop {
+            let len = self.inner.peek(&mut scratch)?;
+            if let Some(plaintext) = self.open_packet(&scratch[..len]) {
+                let copy_len = buf.len().min(plaintext.len());
+                buf[..copy_len].copy_from_slice(&plaintext[..copy_len]);
+                return Ok(plaintext.len());
+            }
+            // `peek` doesn't consume, so a failing datagram would be peeked forever otherwise —
+            // consume it here, the same "drop it and move on" treatment a failed checksum gets in
+            // `crate::rtp::accept_thread`.
+            let _ = self.inner.recv(&mut scratch);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::UdpSocket;
+
+    use super::*;
+
+    fn loopback_pair() -> (UdpSocket, UdpSocket) {
+        let a = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let b = UdpSocket::bind("127.0.0.1:0").unwrap();
+        a.connect(b.local_addr().unwrap()).unwrap();
+        b.connect(a.local_addr().unwrap()).unwrap();
+        (a, b)
+    }
+
+    /// Mirrors `crate::rtp::accept_thread`, which `peek`s only `size_of::<PacketHeader>()` bytes —
+    /// far smaller than a real sealed packet — to read the sequence number/SSRC before deciding
+    /// whether to do a full `recv`. A `peek` that sizes its inner scratch off that small buffer
+    /// (rather than a full-datagram-sized one) would truncate the sealed ciphertext+tag and fail
+    /// Poly1305 verification for every packet, every time.
+    #[test]
+    fn peek_with_small_buffer_still_verifies_full_packet() {
+        // `crate::rtp::PacketHeader`'s actual size, kept as a literal here so this test doesn't
+        // need to pull in all of `rtp` just to read it off the struct.
+        const HEADER_PREFIX_BYTES: usize = 21;
+
+        let (sender_sock, receiver_sock) = loopback_pair();
+        let key = [7u8; aead::KEY_BYTES];
+        let salt = [3u8; aead::NONCE_BYTES - 4];
+
+        let sender = AuthenticatedEncryptedTransport::new(UdpTransport::new(sender_sock), key, salt);
+        let receiver = AuthenticatedEncryptedTransport::new(UdpTransport::new(receiver_sock), key, salt);
+
+        let mut packet = vec![0u8; SEQUENCE_NUMBER_BYTES];
+        packet.extend_from_slice(&[0xABu8; HEADER_PREFIX_BYTES + 200]);
+        sender.send(&packet).unwrap();
+
+        // `accept_thread` only peeks enough to see the header, well short of the full sealed
+        // packet's ciphertext+tag.
+        let mut header_prefix = vec![0u8; HEADER_PREFIX_BYTES];
+        let peeked_len = receiver.peek(&mut header_prefix).unwrap();
+        assert_eq!(peeked_len, packet.len());
+        assert_eq!(&header_prefix[..], &packet[..header_prefix.len()]);
+
+        // The datagram must still be there afterwards (peek doesn't consume it) and `recv` the
+        // full thing correctly.
+        let mut full = vec![0u8; packet.len()];
+        let recv_len = receiver.recv(&mut full).unwrap();
+        assert_eq!(recv_len, packet.len());
+        assert_eq!(full, packet);
+    }
+}
@@ -12,7 +12,7 @@ use sdl2::pixels::Color;
 ⠀⠀⣿⠀⠀
 */
 
-const WPM_SATURATION: f64 = 70.0;
+pub(crate) const WPM_SATURATION: f64 = 70.0;
 const WORST_PACKET_DROP: u32 = 2 * (u32::MAX / 5); // 40% drop rate at 0 WPM
 
 pub fn wpm_to_drop_amt(wpm: f64) -> u32 {
@@ -47,6 +47,69 @@ pub fn wpm_to_jpeg_quality(wpm: f64) -> f64 {
     WORST_JPEG_QUALITY - (WORST_JPEG_QUALITY - BEST_JPEG_QUALITY) * f64::powi(1.0 - wpm_ratio, 1)
 }
 
+/// Highest sum of squared differences over all six planes of a macroblock, at 0 WPM, still treated as "no visible change" by
+/// [`crate::video::encode_frame_macroblock`]'s skip decision. Generous, so poor typing
+/// performance freezes most of the frame in place, complementing `wpm_to_jpeg_quality`'s spatial
+/// degradation with temporal degradation.
+const WORST_SKIP_THRESHOLD: u32 = 4000;
+/// Lowest skip threshold (same whole-macroblock squared-difference measure), at saturation WPM: only near-exact matches get skipped, so motion
+/// stays smooth.
+const BEST_SKIP_THRESHOLD: u32 = 50;
+
+pub fn wpm_to_skip_threshold(wpm: f64) -> u32 {
+    let clipped_wpm = wpm.min(WPM_SATURATION);
+
+    let wpm_ratio = (WPM_SATURATION - clipped_wpm) / WPM_SATURATION;
+    (BEST_SKIP_THRESHOLD as f64 + (WORST_SKIP_THRESHOLD - BEST_SKIP_THRESHOLD) as f64 * wpm_ratio) as u32
+}
+
+/// Highest total macroblock SSD, at 0 WPM, still treated as cheap enough to replace with
+/// [`crate::video::solid_fill_color`]'s single averaged color instead of the full DCT+quantize
+/// path — see [`crate::video::decide_macroblock`]. Always above [`WORST_SKIP_THRESHOLD`], since a
+/// block has to have changed more to need full detail than to need nothing at all.
+const WORST_FILL_THRESHOLD: u32 = 40_000;
+/// Lowest fill-vs-code threshold, at saturation WPM: only a slight average-color difference gets
+/// approximated; anything past that goes through full coding.
+const BEST_FILL_THRESHOLD: u32 = 500;
+
+pub fn wpm_to_fill_threshold(wpm: f64) -> u32 {
+    let clipped_wpm = wpm.min(WPM_SATURATION);
+
+    let wpm_ratio = (WPM_SATURATION - clipped_wpm) / WPM_SATURATION;
+    (BEST_FILL_THRESHOLD as f64 + (WORST_FILL_THRESHOLD - BEST_FILL_THRESHOLD) as f64 * wpm_ratio) as u32
+}
+
+/// Fewest codewords [`crate::video::build_codebook`] trains at 0 WPM — a small, coarse palette
+/// that makes quantization-starvation artifacts obvious, the VQ path's analogue of
+/// [`wpm_to_jpeg_quality`]'s worst case.
+const WORST_VQ_CODEBOOK_SIZE: usize = 16;
+/// Most codewords trained at saturation WPM: enough palette resolution that reconstruction looks
+/// close to the source.
+const BEST_VQ_CODEBOOK_SIZE: usize = 256;
+
+pub fn wpm_to_vq_codebook_size(wpm: f64) -> usize {
+    let clipped_wpm = wpm.min(WPM_SATURATION);
+
+    let wpm_ratio = clipped_wpm / WPM_SATURATION;
+    (WORST_VQ_CODEBOOK_SIZE as f64 + (BEST_VQ_CODEBOOK_SIZE - WORST_VQ_CODEBOOK_SIZE) as f64 * wpm_ratio) as usize
+}
+
+/// Worst case `quantization_shift` (see [`crate::audio_codec::encode`]), at 0 WPM.
+const WORST_AUDIO_QUANTIZATION_SHIFT: u32 = crate::audio_codec::MAX_QUANTIZATION_SHIFT;
+/// Best case `quantization_shift`, at saturation WPM: samples round-trip at full resolution.
+const BEST_AUDIO_QUANTIZATION_SHIFT: u32 = 0;
+
+/// Maps WPM to the audio codec's bitrate knob, same saturation curve as [`wpm_to_jpeg_quality`]:
+/// under keymashing, audio gracefully loses sample resolution (and so packs smaller) rather than
+/// the RTP layer just dropping whole packets.
+pub fn wpm_to_audio_quantization_shift(wpm: f64) -> u32 {
+    let clipped_wpm = wpm.min(WPM_SATURATION);
+
+    let wpm_ratio = (WPM_SATURATION - clipped_wpm) / WPM_SATURATION;
+    (BEST_AUDIO_QUANTIZATION_SHIFT as f64
+        + (WORST_AUDIO_QUANTIZATION_SHIFT - BEST_AUDIO_QUANTIZATION_SHIFT) as f64 * wpm_ratio) as u32
+}
+
 pub const CHART_DATA_LENGTH: usize = 1000;
 
 #[derive(Debug)]
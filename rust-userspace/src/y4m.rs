@@ -0,0 +1,56 @@
+//! A minimal YUV4MPEG2 (Y4M) muxer for saving the receiver's reconstructed output to a file any
+//! standard player/`ffmpeg` can open directly — unlike [`crate::capture`]'s recording, which
+//! replays the coded macroblock stream through the decoder again, this just writes out whatever
+//! pixels the pipeline actually reconstructed each frame (see [`crate::video::reconstruct_frame`]).
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+/// Writes a YUV4MPEG2 stream: one header line, then one `FRAME` chunk per
+/// [`Y4mWriter::write_frame`] call.
+pub struct Y4mWriter {
+    file: BufWriter<File>,
+    width: usize,
+    height: usize,
+}
+
+impl Y4mWriter {
+    /// Opens `path` and writes the stream header (4:2:2 chroma, matching the packed YUYV422
+    /// buffers this writer accepts); `fps` is rounded to a `numerator:1000` fraction, close enough
+    /// for playback at typical frame rates (e.g. 30, 29.97).
+    pub fn create(path: impl AsRef<Path>, width: usize, height: usize, fps: f64) -> io::Result<Self> {
+        let mut file = BufWriter::new(File::create(path)?);
+        let fps_numerator = (fps * 1000.0).round() as u64;
+        writeln!(file, "YUV4MPEG2 W{width} H{height} F{fps_numerator}:1000 Ip A1:1 C422")?;
+        Ok(Self { file, width, height })
+    }
+
+    /// Writes one frame of packed YUYV422 bytes (the same layout [`crate::video::MutableYUVFrame`]
+    /// writes into), converting it to Y4M's planar 4:2:2 layout — a full-resolution Y plane
+    /// followed by half-width U and V planes — as it goes.
+    pub fn write_frame(&mut self, yuyv: &[u8]) -> io::Result<()> {
+        assert_eq!(yuyv.len(), self.width * self.height * 2, "frame buffer doesn't match this writer's dimensions");
+
+        writeln!(self.file, "FRAME")?;
+        for chunk in yuyv.chunks_exact(4) {
+            self.file.write_all(&[chunk[0], chunk[2]])?;
+        }
+        for chunk in yuyv.chunks_exact(4) {
+            self.file.write_all(&[chunk[1]])?;
+        }
+        for chunk in yuyv.chunks_exact(4) {
+            self.file.write_all(&[chunk[3]])?;
+        }
+        Ok(())
+    }
+
+    /// Flushes and closes the file. Call once, on `Quit` or once replay/the packet stream is
+    /// exhausted — an unflushed `BufWriter` can silently drop the last frames on drop if the
+    /// final `write_all` happened to straddle a buffer boundary and the process exits abruptly.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}